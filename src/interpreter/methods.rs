@@ -0,0 +1,740 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use regex::Regex;
+
+use crate::{modules::math, Error, RuntimeError};
+
+use super::{scope::Scope, types::{Value, parse_number, own_fields}, call_function};
+
+const ARRAY_METHODS: [&str; 23] = [
+    "push", "pop", "shift", "unshift", "copyWithin", "dedupConsecutive", "rotate", "toString", "enumerate",
+    "map", "filter", "reduce", "scan", "forEach",
+    "minBy", "maxBy",
+    "intersect", "union", "difference",
+    "sample", "binarySearch", "frequencies", "interleave"
+];
+
+const STRING_METHODS: [&str; 12] = [
+    "split", "splitLines", "trim", "toUpperCase", "toLowerCase",
+    "indexOf", "includes", "slice", "replace", "repeat", "matches",
+    "toNumber"
+];
+
+const OBJECT_METHODS: [&str; 4] = ["keys", "values", "entries", "has"];
+
+const NUMBER_METHODS: [&str; 2] = ["toFixed", "toPrecision"];
+
+// built-in methods are looked up before regular fields
+pub fn has_method(value: &Value, name: &str) -> bool {
+    match value {
+        Value::Array(_values) => ARRAY_METHODS.contains(&name),
+        Value::String(_val) => STRING_METHODS.contains(&name),
+        Value::Object(_map) => OBJECT_METHODS.contains(&name),
+        // a method of the class wins over the built-in one
+        Value::Instance(_, fields) => OBJECT_METHODS.contains(&name) && !fields.contains_key(name),
+        Value::Integer(_) | Value::Number(_) => NUMBER_METHODS.contains(&name),
+        _ => false
+    }
+}
+
+pub fn call_method(value: &mut Value, name: &str, args: Vec<Value>, scope: &mut Scope) -> Result<Value, Error> {
+    match value {
+        Value::Array(array) => array_method(array, name, args, scope),
+        Value::String(string) => string_method(string, name, args),
+        Value::Object(map) => object_method(map, name, args),
+        // like an object of its fields
+        Value::Instance(_, fields) => object_method(&own_fields(fields), name, args),
+        Value::Integer(num) => number_method(*num as f64, name, args),
+        Value::Number(num) => number_method(*num, name, args),
+        _ => Ok(Value::Null)
+    }
+}
+
+fn number_method(num: f64, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+    match name {
+        // fixed number of decimals, halves are rounded away from zero
+        "toFixed" => {
+            let digits = args.first().map(|digits| digits.as_number()).unwrap_or(0.0);
+            if !(0.0..=100.0).contains(&digits) {
+                return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Precision must be between 0 and 100, got {}", digits)))
+            }
+
+            let digits = digits as usize;
+            let factor = 10f64.powi(digits as i32);
+            let rounded = match num.is_finite() && (num * factor).is_finite() {
+                true => (num * factor).round() / factor,
+                false => num
+            };
+
+            Ok(Value::String(format!("{:.*}", digits, rounded)))
+        },
+        // `digits` significant figures, in exponent form (`1.2e+21`) when the
+        // exponent is below -6 or doesn't fit into the digits
+        "toPrecision" => {
+            let digits = args.first().map(|digits| digits.as_number()).unwrap_or(f64::NAN);
+            if !(1.0..=100.0).contains(&digits) {
+                return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Precision must be between 1 and 100, got {}", Value::Number(digits).as_string())))
+            }
+
+            if !num.is_finite() {
+                return Ok(Value::String(Value::Number(num).as_string()))
+            }
+
+            let digits = digits as i32;
+            // halves are rounded away from zero, like toFixed
+            let round = |exponent: i32| {
+                let factor = 10f64.powi(digits - 1 - exponent);
+                match (num * factor).is_finite() {
+                    true => (num * factor).round() / factor,
+                    false => num
+                }
+            };
+
+            let mut exponent = if num == 0.0 { 0 } else { num.abs().log10().floor() as i32 };
+            let mut rounded = round(exponent);
+            // 99.99 with 3 digits becomes 100
+            if rounded.abs() >= 10f64.powi(exponent + 1) {
+                exponent += 1;
+                rounded = round(exponent);
+            }
+
+            if exponent < -6 || exponent >= digits {
+                let scientific = format!("{:.*e}", digits as usize - 1, rounded);
+                let mantissa = scientific.split('e').next().unwrap_or_default();
+                let sign = if exponent < 0 { "-" } else { "+" };
+
+                return Ok(Value::String(format!("{}e{}{}", mantissa, sign, exponent.abs())))
+            }
+
+            Ok(Value::String(format!("{:.*}", (digits - 1 - exponent) as usize, rounded)))
+        },
+        _ => Ok(Value::Null)
+    }
+}
+
+fn object_method(map: &BTreeMap<String, Box<Value>>, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+    match name {
+        "keys" => Ok(Value::Array(map.keys().map(|key| Box::new(Value::String(key.to_owned()))).collect())),
+        "values" => Ok(Value::Array(map.values().cloned().collect())),
+        // [key, value] pairs
+        "entries" => Ok(Value::Array(
+            map
+            .iter()
+            .map(|(key, value)| Box::new(Value::Array(vec![Box::new(Value::String(key.to_owned())), value.to_owned()])))
+            .collect()
+        )),
+        "has" => {
+            let key = args.first().map(|key| key.as_string()).unwrap_or_default();
+
+            Ok(Value::Boolean(map.contains_key(&key)))
+        },
+        _ => Ok(Value::Null)
+    }
+}
+
+fn string_method(string: &str, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+
+    match name {
+        "split" => {
+            let parts: Vec<String> = match args.first() {
+                None | Some(Value::Null) => vec![string.to_string()],
+                Some(separator) => match separator.as_string().as_str() {
+                    "" => string.chars().map(|c| c.to_string()).collect(),
+                    separator => string.split(separator).map(|part| part.to_string()).collect()
+                }
+            };
+
+            Ok(Value::Array(parts.into_iter().map(|part| Box::new(Value::String(part))).collect()))
+        },
+        // lines end at \n or \r\n, which are dropped unless keepEnds is true.
+        // a last line without a terminator is kept, no empty line follows a final one
+        "splitLines" => {
+            let keep_ends = arg(0).as_bool();
+
+            Ok(Value::Array(
+                string
+                .split_inclusive('\n')
+                .map(|line| match keep_ends {
+                    true => line,
+                    false => line.strip_suffix('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).unwrap_or(line)
+                })
+                .map(|line| Box::new(Value::String(line.to_string())))
+                .collect()
+            ))
+        },
+        "trim" => Ok(Value::String(string.trim().to_string())),
+        "toUpperCase" => Ok(Value::String(string.to_uppercase())),
+        "toLowerCase" => Ok(Value::String(string.to_lowercase())),
+        "indexOf" => {
+            let sub = arg(0).as_string();
+
+            Ok(Value::Integer(match string.find(&sub) {
+                Some(index) => string[..index].chars().count() as i64,
+                None => -1
+            }))
+        },
+        "includes" => Ok(Value::Boolean(string.contains(&arg(0).as_string()))),
+        "slice" => {
+            let chars: Vec<char> = string.chars().collect();
+            let start = clamp_index(&arg(0), 0, chars.len());
+            let end = clamp_index(&arg(1), chars.len(), chars.len()).max(start);
+
+            Ok(Value::String(chars[start..end].iter().collect()))
+        },
+        "replace" => Ok(Value::String(string.replacen(&arg(0).as_string(), &arg(1).as_string(), 1))),
+        "repeat" => Ok(Value::String(string.repeat(arg(0).as_number().max(0.0) as usize))),
+        // true when the pattern matches anywhere, anchor it with ^...$ for a full match
+        "matches" => {
+            let pattern = arg(0).as_string();
+
+            match Regex::new(&pattern) {
+                Ok(regex) => Ok(Value::Boolean(regex.is_match(string))),
+                Err(_e) => Err(Error::runtime(RuntimeError::InvalidArgument, format!("Invalid pattern: {}", pattern)))
+            }
+        },
+        // unlike num(), anything that is not a number gives null
+        "toNumber" => Ok(parse_number(string.trim()).map(Value::Number).unwrap_or(Value::Null)),
+        _ => Ok(Value::Null)
+    }
+}
+
+#[allow(clippy::vec_box)]
+fn array_method(array: &mut Vec<Box<Value>>, name: &str, args: Vec<Value>, scope: &mut Scope) -> Result<Value, Error> {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+
+    match name {
+        "push" => {
+            array.extend(args.into_iter().map(Box::new));
+
+            Ok(Value::Integer(array.len() as i64))
+        },
+        "pop" => Ok(array.pop().map(|item| *item).unwrap_or(Value::Null)),
+        "shift" => {
+            if array.is_empty() {
+                return Ok(Value::Null)
+            }
+
+            Ok(*array.remove(0))
+        },
+        "unshift" => {
+            array.splice(0..0, args.into_iter().map(Box::new));
+
+            Ok(Value::Integer(array.len() as i64))
+        },
+        // copies array[start..end] over the elements starting at target
+        "copyWithin" => {
+            let len = array.len();
+            let target = clamp_index(&arg(0), 0, len);
+            let start = clamp_index(&arg(1), 0, len);
+            let end = clamp_index(&arg(2), len, len).max(start);
+            let count = (end - start).min(len - target);
+
+            let block = array[start..start + count].to_vec();
+            array.splice(target..target + count, block);
+
+            Ok(Value::Array(array.to_owned()))
+        },
+        // drops only elements == to the one right before them
+        "dedupConsecutive" => {
+            let mut deduped = array.to_owned();
+            deduped.dedup_by(|a, b| a.equals(b));
+
+            Ok(Value::Array(deduped))
+        },
+        // rotates left by n, right for negative n
+        "rotate" => {
+            let mut rotated = array.to_owned();
+
+            if !rotated.is_empty() {
+                let len = rotated.len() as f64;
+                let n = arg(0).as_number().trunc().rem_euclid(len);
+
+                rotated.rotate_left(if n.is_nan() { 0 } else { n as usize });
+            }
+
+            Ok(Value::Array(rotated))
+        },
+        "toString" => {
+            let separator = match arg(0) {
+                Value::Null => ",".to_string(),
+                separator => separator.as_string()
+            };
+
+            Ok(Value::String(array.iter().map(|item| item.as_string()).collect::<Vec<_>>().join(&separator)))
+        },
+        // [index, value] pairs
+        "enumerate" => Ok(Value::Array(
+            array
+            .iter()
+            .enumerate()
+            .map(|(i, item)| Box::new(Value::Array(vec![Box::new(Value::Integer(i as i64)), item.to_owned()])))
+            .collect()
+        )),
+        // callbacks get the element and its index
+        "map" => {
+            let mut mapped = vec![];
+
+            for (i, item) in array.iter().enumerate() {
+                mapped.push(Box::new(call_function(arg(0), vec![*item.to_owned(), Value::Integer(i as i64)], scope)?));
+            }
+
+            Ok(Value::Array(mapped))
+        },
+        "filter" => {
+            let mut filtered = vec![];
+
+            for (i, item) in array.iter().enumerate() {
+                if call_function(arg(0), vec![*item.to_owned(), Value::Integer(i as i64)], scope)?.as_bool() {
+                    filtered.push(item.to_owned());
+                }
+            }
+
+            Ok(Value::Array(filtered))
+        },
+        "reduce" => {
+            // without an initial value the first element is used
+            let mut items = array.iter().enumerate();
+            let mut acc = match args.get(1) {
+                Some(init) => init.to_owned(),
+                None => match items.next() {
+                    Some((_, first)) => *first.to_owned(),
+                    None => return Ok(Value::Null)
+                }
+            };
+
+            for (i, item) in items {
+                acc = call_function(arg(0), vec![acc, *item.to_owned(), Value::Integer(i as i64)], scope)?;
+            }
+
+            Ok(acc)
+        },
+        // every accumulator reduce goes through, one per element;
+        // without an initial value the first element is the first accumulator
+        "scan" => {
+            let mut items = array.iter().enumerate();
+            let mut scanned = vec![];
+            let mut acc = match args.get(1) {
+                Some(init) => init.to_owned(),
+                None => match items.next() {
+                    Some((_, first)) => {
+                        scanned.push(first.to_owned());
+                        *first.to_owned()
+                    },
+                    None => return Ok(Value::Array(scanned))
+                }
+            };
+
+            for (i, item) in items {
+                acc = call_function(arg(0), vec![acc, *item.to_owned(), Value::Integer(i as i64)], scope)?;
+                scanned.push(Box::new(acc.clone()));
+            }
+
+            Ok(Value::Array(scanned))
+        },
+        "forEach" => {
+            for (i, item) in array.iter().enumerate() {
+                call_function(arg(0), vec![*item.to_owned(), Value::Integer(i as i64)], scope)?;
+            }
+
+            Ok(Value::Null)
+        },
+        // the element with the smallest/largest key, the first one on ties
+        "minBy" | "maxBy" => {
+            let wanted = if name == "minBy" { Ordering::Less } else { Ordering::Greater };
+            let mut best: Option<(Value, Value)> = None;
+
+            for item in array.iter() {
+                let key = call_function(arg(0), vec![*item.to_owned()], scope)?;
+
+                let is_better = match &best {
+                    Some((_, best_key)) => key.compare(best_key.to_owned()) == wanted,
+                    None => true
+                };
+
+                if is_better {
+                    best = Some((*item.to_owned(), key));
+                }
+            }
+
+            Ok(best.map(|(item, _)| item).unwrap_or(Value::Null))
+        },
+        // set operations compare with ==, keep the order of this array
+        // (union appends the new elements of the other one) and drop duplicates
+        "intersect" | "union" | "difference" => {
+            let other = match arg(0) {
+                Value::Array(other) => other,
+                value => return Err(Error::runtime(RuntimeError::TypeMismatch, format!("Expected array, got {}", value.type_name())))
+            };
+            let in_other = |item: &Value| other.iter().any(|value| value.equals(item));
+
+            let candidates: Vec<&Box<Value>> = match name {
+                "intersect" => array.iter().filter(|item| in_other(item)).collect(),
+                "difference" => array.iter().filter(|item| !in_other(item)).collect(),
+                _ => array.iter().chain(other.iter()).collect()
+            };
+
+            let mut result: Vec<Box<Value>> = vec![];
+            for item in candidates {
+                if !result.iter().any(|value| value.equals(item)) {
+                    result.push(item.to_owned());
+                }
+            }
+
+            Ok(Value::Array(result))
+        },
+        // sample() is a random element (null for an empty array),
+        // sample(n) is n different elements, more than the array holds is an error
+        "sample" => {
+            let amount = match arg(0) {
+                Value::Null => {
+                    let picked = math::sample_indices(array.len(), array.len().min(1));
+
+                    return Ok(picked.first().map(|i| *array[*i].to_owned()).unwrap_or(Value::Null))
+                },
+                amount => amount.as_number()
+            };
+
+            if !(0.0..=array.len() as f64).contains(&amount) {
+                return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Cannot sample {} elements from an array of {}", Value::Number(amount).as_string(), array.len())))
+            }
+
+            Ok(Value::Array(math::sample_indices(array.len(), amount as usize).into_iter().map(|i| array[i].to_owned()).collect()))
+        },
+        // the array has to be sorted. the index of the value when it's there,
+        // otherwise -(insertion point) - 1, so a miss is always negative
+        "binarySearch" => {
+            let target = arg(0);
+
+            match array.binary_search_by(|item| item.compare(target.to_owned())) {
+                Ok(index) => Ok(Value::Integer(index as i64)),
+                Err(index) => Ok(Value::Integer(-(index as i64) - 1))
+            }
+        },
+        // how often each distinct element occurs, keyed by the element as a string.
+        // elements are told apart with deepEqual, ones that print the same share a key
+        "frequencies" => {
+            let mut groups: Vec<(&Value, i64)> = vec![];
+
+            for item in array.iter() {
+                match groups.iter_mut().find(|(value, _)| value.deep_equals(item)) {
+                    Some((_, count)) => *count += 1,
+                    None => groups.push((item, 1))
+                }
+            }
+
+            let mut counts: BTreeMap<String, Box<Value>> = BTreeMap::new();
+            for (value, count) in groups {
+                let total = counts.get(&value.as_string()).map(|total| total.as_number() as i64).unwrap_or(0) + count;
+                counts.insert(value.as_string(), Box::new(Value::Integer(total)));
+            }
+
+            Ok(Value::Object(counts))
+        },
+        // [a0, b0, a1, b1, ...], what's left of the longer array goes at the end
+        "interleave" => {
+            let other = match arg(0) {
+                Value::Array(other) => other,
+                value => return Err(Error::runtime(RuntimeError::TypeMismatch, format!("Expected array, got {}", value.type_name())))
+            };
+
+            let mut result: Vec<Box<Value>> = vec![];
+            for i in 0..array.len().max(other.len()) {
+                result.extend(array.get(i).cloned());
+                result.extend(other.get(i).cloned());
+            }
+
+            Ok(Value::Array(result))
+        },
+        _ => Ok(Value::Null)
+    }
+}
+
+// like Value::as_index, but out of range positions are clamped to 0..=len
+// and null gives the `default` position
+fn clamp_index(value: &Value, default: usize, len: usize) -> usize {
+    let index = match value {
+        Value::Null => return default,
+        value => value.as_number()
+    };
+
+    if index.is_nan() {
+        return 0
+    }
+
+    if index.is_sign_negative() {
+        return (len as f64 + index).max(0.0) as usize
+    }
+
+    index.min(len as f64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval;
+
+    use super::super::types::Value;
+
+    fn ints(values: &[i64]) -> Value {
+        Value::Array(values.iter().map(|value| Box::new(Value::Integer(*value))).collect())
+    }
+
+    #[test]
+    fn dedup_consecutive_keeps_separated_repeats() {
+        let scope = eval("
+            let numbers = [1, 1, 2, 2, 1].dedupConsecutive()
+            let mixed = [1, 1.0, {a: [1]}, {a: [1]}, 2].dedupConsecutive().length
+        ").unwrap();
+
+        assert_eq!(scope.get("numbers".to_string()), ints(&[1, 2, 1]));
+        // 1 == 1.0 and objects are compared by their fields
+        assert_eq!(scope.get("mixed".to_string()), Value::Integer(3));
+    }
+
+    #[test]
+    fn interleave_appends_the_rest_of_the_longer_array() {
+        let scope = eval("
+            let longer = [1, 3, 5, 7].interleave([2, 4])
+            let shorter = [1].interleave([2, 4, 6])
+            let empty = [].interleave([1, 2])
+        ").unwrap();
+
+        assert_eq!(scope.get("longer".to_string()), ints(&[1, 2, 3, 4, 5, 7]));
+        assert_eq!(scope.get("shorter".to_string()), ints(&[1, 2, 4, 6]));
+        assert_eq!(scope.get("empty".to_string()), ints(&[1, 2]));
+    }
+
+    #[test]
+    fn binary_search_finds_or_gives_the_insertion_point() {
+        let scope = eval("
+            let numbers = [1, 3, 5, 7]
+            let found = numbers.binarySearch(5)
+            let before = numbers.binarySearch(0)
+            let between = numbers.binarySearch(4)
+            let after = numbers.binarySearch(9)
+            let word = ['ant', 'bee', 'cat'].binarySearch('bee')
+        ").unwrap();
+
+        assert_eq!(scope.get("found".to_string()), Value::Integer(2));
+        // -(insertion point) - 1
+        assert_eq!(scope.get("before".to_string()), Value::Integer(-1));
+        assert_eq!(scope.get("between".to_string()), Value::Integer(-3));
+        assert_eq!(scope.get("after".to_string()), Value::Integer(-5));
+        assert_eq!(scope.get("word".to_string()), Value::Integer(1));
+    }
+
+    #[test]
+    fn push_grows_the_length() {
+        let scope = eval("
+            let numbers = [1, 2]
+            numbers.push(3)
+            let length = numbers.length
+        ").unwrap();
+
+        assert_eq!(scope.get("numbers".to_string()), ints(&[1, 2, 3]));
+        assert_eq!(scope.get("length".to_string()), Value::Integer(3));
+    }
+
+    #[test]
+    fn object_methods() {
+        let scope = eval("
+            let point = { x: 1, y: 2 }
+            let keys = point.keys()
+            let values = point.values()
+            let entries = point.entries()
+            let hasX = point.has('x')
+            let hasZ = point.has('z')
+        ").unwrap();
+
+        let string = |s: &str| Box::new(Value::String(s.to_string()));
+
+        assert_eq!(scope.get("keys".to_string()), Value::Array(vec![string("x"), string("y")]));
+        assert_eq!(scope.get("values".to_string()), ints(&[1, 2]));
+        assert_eq!(scope.get("entries".to_string()), Value::Array(vec![
+            Box::new(Value::Array(vec![string("x"), Box::new(Value::Integer(1))])),
+            Box::new(Value::Array(vec![string("y"), Box::new(Value::Integer(2))]))
+        ]));
+        assert_eq!(scope.get("hasX".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("hasZ".to_string()), Value::Boolean(false));
+    }
+
+    #[test]
+    fn to_fixed_without_decimals_rounds() {
+        let scope = eval("
+            let up = (2.6).toFixed(0)
+            let down = (2.4).toFixed(0)
+        ").unwrap();
+
+        assert_eq!(scope.get("up".to_string()), Value::String("3".to_string()));
+        assert_eq!(scope.get("down".to_string()), Value::String("2".to_string()));
+    }
+
+    #[test]
+    fn max_by_score() {
+        let scope = eval("
+            fun score(player) { return player.score }
+            let players = [{ name: 'ann', score: 3 }, { name: 'bob', score: 9 }, { name: 'cy', score: 5 }]
+            let best = players.maxBy(score).name
+            let worst = players.minBy(score).name
+            let none = [].maxBy(score)
+        ").unwrap();
+
+        assert_eq!(scope.get("best".to_string()), Value::String("bob".to_string()));
+        assert_eq!(scope.get("worst".to_string()), Value::String("ann".to_string()));
+        assert_eq!(scope.get("none".to_string()), Value::Null);
+    }
+
+    #[test]
+    fn to_number() {
+        let scope = eval("
+            let whole = '42'.toNumber()
+            let padded = ' 4.5 '.toNumber()
+            let garbage = 'abc'.toNumber()
+        ").unwrap();
+
+        assert_eq!(scope.get("whole".to_string()).as_number(), 42.0);
+        assert_eq!(scope.get("padded".to_string()).as_number(), 4.5);
+        assert_eq!(scope.get("garbage".to_string()), Value::Null);
+    }
+
+    #[test]
+    fn string_basics() {
+        let scope = eval("
+            let parts = ' a,b '.trim().split(',')
+            let upper = 'Coco'.toUpperCase()
+            let lower = 'Coco'.toLowerCase()
+        ").unwrap();
+
+        assert_eq!(scope.get("parts".to_string()), Value::Array(vec![
+            Box::new(Value::String("a".to_string())),
+            Box::new(Value::String("b".to_string()))
+        ]));
+        assert_eq!(scope.get("upper".to_string()), Value::String("COCO".to_string()));
+        assert_eq!(scope.get("lower".to_string()), Value::String("coco".to_string()));
+    }
+
+    #[test]
+    fn copy_within() {
+        let scope = eval("
+            let tail = [1, 2, 3, 4, 5].copyWithin(0, 3)
+            let middle = [1, 2, 3, 4, 5].copyWithin(1, 3, 4)
+        ").unwrap();
+
+        assert_eq!(scope.get("tail".to_string()), ints(&[4, 5, 3, 4, 5]));
+        assert_eq!(scope.get("middle".to_string()), ints(&[1, 4, 3, 4, 5]));
+    }
+
+    #[test]
+    fn rotate() {
+        let scope = eval("
+            let left = [1, 2, 3, 4].rotate(1)
+            let right = [1, 2, 3, 4].rotate(-1)
+            let around = [1, 2, 3, 4].rotate(5)
+        ").unwrap();
+
+        assert_eq!(scope.get("left".to_string()), ints(&[2, 3, 4, 1]));
+        assert_eq!(scope.get("right".to_string()), ints(&[4, 1, 2, 3]));
+        assert_eq!(scope.get("around".to_string()), ints(&[2, 3, 4, 1]));
+    }
+
+    #[test]
+    fn to_string_separator() {
+        let scope = eval("
+            let dashed = [1, 2, 3].toString('-')
+            let plain = [1, 2, 3].toString()
+        ").unwrap();
+
+        assert_eq!(scope.get("dashed".to_string()), Value::String("1-2-3".to_string()));
+        assert_eq!(scope.get("plain".to_string()), Value::String("1,2,3".to_string()));
+    }
+
+    #[test]
+    fn scan_keeps_every_step() {
+        let scope = eval("
+            fun add(total, x) { return total + x }
+            let totals = [1, 2, 3].scan(add, 0)
+            let empty = [].scan(add, 0)
+        ").unwrap();
+
+        assert_eq!(scope.get("totals".to_string()), ints(&[1, 3, 6]));
+        assert_eq!(scope.get("empty".to_string()), ints(&[]));
+    }
+
+    #[test]
+    fn matches_a_pattern() {
+        let scope = eval("
+            let anywhere = 'a1b22'.matches('[0-9]+')
+            let anchored = 'a1b22'.matches('^[0-9]+$')
+        ").unwrap();
+
+        assert_eq!(scope.get("anywhere".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("anchored".to_string()), Value::Boolean(false));
+        assert!(eval("'a'.matches('(')").is_err());
+    }
+
+    #[test]
+    fn set_operations() {
+        let scope = eval("
+            let both = [1, 2, 3].intersect([2, 3, 4])
+            let either = [1, 2, 2].union([2, 3])
+            let only = [1, 2, 3].difference([2])
+        ").unwrap();
+
+        assert_eq!(scope.get("both".to_string()), ints(&[2, 3]));
+        assert_eq!(scope.get("either".to_string()), ints(&[1, 2, 3]));
+        assert_eq!(scope.get("only".to_string()), ints(&[1, 3]));
+    }
+
+    #[test]
+    fn to_precision() {
+        let scope = eval("
+            let pi = (3.14159).toPrecision(3)
+            let whole = (1234.5).toPrecision(2)
+            let small = (0.000123456).toPrecision(2)
+        ").unwrap();
+
+        assert_eq!(scope.get("pi".to_string()), Value::String("3.14".to_string()));
+        // the exponent doesn't fit into two digits
+        assert_eq!(scope.get("whole".to_string()), Value::String("1.2e+3".to_string()));
+        assert_eq!(scope.get("small".to_string()), Value::String("0.00012".to_string()));
+        assert!(eval("(1.5).toPrecision(0)").is_err());
+    }
+
+    #[test]
+    fn enumerate_pairs_indexes() {
+        let scope = eval("
+            let pairs = ['a', 'b'].enumerate()
+        ").unwrap();
+
+        assert_eq!(scope.get("pairs".to_string()), Value::Array(vec![
+            Box::new(Value::Array(vec![Box::new(Value::Integer(0)), Box::new(Value::String("a".to_string()))])),
+            Box::new(Value::Array(vec![Box::new(Value::Integer(1)), Box::new(Value::String("b".to_string()))]))
+        ]));
+    }
+
+    #[test]
+    fn split_lines_with_crlf() {
+        let scope = eval("
+            let text = 'one\r\ntwo\nthree\r\n'
+            let lines = text.splitLines()
+            let kept = text.splitLines(true)
+        ").unwrap();
+
+        let strings = |values: &[&str]| Value::Array(values.iter().map(|s| Box::new(Value::String(s.to_string()))).collect());
+
+        assert_eq!(scope.get("lines".to_string()), strings(&["one", "two", "three"]));
+        assert_eq!(scope.get("kept".to_string()), strings(&["one\r\n", "two\n", "three\r\n"]));
+    }
+
+    #[test]
+    fn frequencies_count_repeats() {
+        let scope = eval("
+            let counts = ['a', 'b', 'a'].frequencies()
+            let a = counts.a
+            let b = counts.b
+        ").unwrap();
+
+        assert_eq!(scope.get("a".to_string()), Value::Integer(2));
+        assert_eq!(scope.get("b".to_string()), Value::Integer(1));
+    }
+}
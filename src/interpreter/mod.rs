@@ -1,32 +1,25 @@
 use core::panic;
-use std::{collections::{BTreeMap}, cmp::Ordering};
+use std::{collections::{BTreeMap}, cmp::Ordering, rc::Rc};
 
-use crate::{parser::{ Node, SwitchCase, LogicalOp, BinaryOp, UnaryOp, AssignmentOp }, modules::import_module, Error};
+use crate::{parser::{ Node, SwitchCase, LogicalOp, BinaryOp, UnaryOp, AssignmentOp }, modules::{import_module, units}, Error, RuntimeError};
 
+pub mod methods;
 pub mod scope;
 pub mod types;
 
-use self::{scope::{ Scope }, types::{Value, FieldAccessor, FuncImpl}};
+use self::{scope::{ Scope, Control }, types::{Value, FieldAccessor, FuncImpl, own_fields}};
 
 pub struct Interpreter {}
 
 pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
     match node {
         Node::ImportPlaceholder(lib, placeholder) => {
-            let module = import_module(lib.as_str(), None);
+            let module = import_module(lib.as_str(), scope, None)?;
             scope.set(placeholder, module);
             Ok(Value::Null)
         },
         Node::ImportObjects(lib, objects) => {
-            // FIXME
-            let module = import_module(lib.as_str(), Some(objects.clone()));
-
-            for obj in objects.iter() {
-                let mut fa = FieldAccessor::new(module.clone(), Vec::from([Value::String(obj.to_string())]));
-                let value = fa.get(scope);
-                scope.set(obj.to_string(), value);
-            }
-
+            import_module(lib.as_str(), scope, Some(objects))?;
             Ok(Value::Null)
         },
         Node::BlockStatement(statements) => {
@@ -35,14 +28,14 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
             
 
             for statement in statements {
-                match *statement {
-                    Node::Return(value) => {
-                        result = walk_tree(*value, scope)?;
-                        break;
-                    },
-                    _ => {
-                        walk_tree(*statement, scope)?;
-                    }
+                walk_tree(*statement, scope)?;
+
+                if let Some(Control::Return(value)) = &scope.control {
+                    result = value.to_owned();
+                }
+
+                if scope.control.is_some() {
+                    break;
                 }
             }
             Ok(result)
@@ -54,15 +47,78 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
                     
                     Ok(scope.set(name, value))
                 },
+                // elements are assigned by position, missing ones are null
+                Node::Array(targets) => {
+                    let values = match walk_tree(*value, scope)? {
+                        Value::Array(values) => values,
+                        value => return Err(Error::runtime(RuntimeError::CannotDestructure, format!("Cannot destructure {} as an array", value.type_name())))
+                    };
+
+                    for (i, target) in targets.into_iter().enumerate() {
+                        match *target {
+                            Node::Var(name) => scope.set(name, values.get(i).map(|value| *value.to_owned()).unwrap_or(Value::Null)),
+                            Node::Rest(name) => scope.set(name, Value::Array(values.iter().skip(i).cloned().collect())),
+                            _ => Value::Null
+                        };
+                    }
+
+                    Ok(Value::Null)
+                },
+                // fields are assigned by name, the rest gets the ones not named
+                Node::Object(targets) => {
+                    let mut fields = match walk_tree(*value, scope)? {
+                        Value::Object(fields) => fields,
+                        Value::Instance(_, fields) => fields,
+                        value => return Err(Error::runtime(RuntimeError::CannotDestructure, format!("Cannot destructure {} as an object", value.type_name())))
+                    };
+
+                    let mut rest = None;
+                    for (key, target) in targets {
+                        match *target {
+                            Node::Var(name) => { scope.set(name, fields.remove(&key).map(|value| *value).unwrap_or(Value::Null)); },
+                            Node::Rest(name) => rest = Some(name),
+                            _ => {}
+                        }
+                    }
+
+                    if let Some(name) = rest {
+                        scope.set(name, Value::Object(fields));
+                    }
+
+                    Ok(Value::Null)
+                },
                 _ => {
                     panic!("Unexpected assign")
                 }
             }
         },
+        // plain assignments always create locals, `global` writes to the top-most scope
+        Node::Global(name, value) => {
+            let value = walk_tree(*value, scope)?;
+
+            Ok(scope.root().set(name, value))
+        },
         Node::AssignOp(op, variable_node, value_node) => {
             let mut initial_value = walk_tree(*variable_node.clone(), scope)?;
+            let previous = initial_value.clone();
             let set_value = walk_tree(*value_node, scope)?;
+
+            // the operators that have a binary counterpart keep integers exact
+            let binary_op = match op {
+                AssignmentOp::PLUSEQ | AssignmentOp::PLUSPLUS => Some(BinaryOp::PLUS),
+                AssignmentOp::MINUSEQ | AssignmentOp::MINUSMINUS => Some(BinaryOp::MINUS),
+                AssignmentOp::MULEQ => Some(BinaryOp::MULTIPLY),
+                AssignmentOp::DIVEQ => Some(BinaryOp::DIVIDE),
+                AssignmentOp::REMEQ => Some(BinaryOp::REMAINDER),
+                AssignmentOp::EXPEQ => Some(BinaryOp::EXPONENT),
+                AssignmentOp::EQ => None
+            };
+            let exact = binary_op.and_then(|binary_op| integer_binary(&binary_op, &initial_value, &set_value));
+
             match op {
+                _ if exact.is_some() => {
+                    initial_value = exact.unwrap();
+                },
                 AssignmentOp::EQ => {
                     initial_value = set_value;
                 },
@@ -86,34 +142,37 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
                 },
                 AssignmentOp::EXPEQ => {
                     initial_value = Value::Number(initial_value.as_number().powf(set_value.as_number()));
+                },
+                AssignmentOp::PLUSPLUS => {
+                    initial_value = Value::Number(initial_value.as_number() + 1.0);
+                },
+                AssignmentOp::MINUSMINUS => {
+                    initial_value = Value::Number(initial_value.as_number() - 1.0);
                 }
             }
 
-            if let Node::Var(name) = *variable_node.clone() {
-                scope.set(name, initial_value.clone());
-            }
-
-            if let Node::FieldAccess(var, indices) = *variable_node {
-                if let Node::Var(name) = *var.clone() {
-                    let var_value = walk_tree(*var, scope)?;
-                    let fields = indices.iter().map(|i| walk_tree(*i.to_owned(), scope).unwrap_or(Value::Null)).collect::<Vec<Value>>();
-                    let mut field_accessor = FieldAccessor::new(var_value, fields);
-                    let value = field_accessor.set(initial_value, scope);
+            assign(*variable_node, initial_value, scope)?;
 
-                    scope.set(name, value);
-                }
+            // like postfix operators elsewhere, a++ gives the value before the change
+            match op {
+                AssignmentOp::PLUSPLUS | AssignmentOp::MINUSMINUS => match previous {
+                    Value::Integer(_) => Ok(previous),
+                    _ => Ok(Value::Number(previous.as_number()))
+                },
+                _ => Ok(Value::Null)
             }
-
-            Ok(Value::Null)
         },
-        Node::Var(name) => Ok(scope.get(name).to_owned()),
+        Node::Var(name) => Ok(scope.get(name)),
+        Node::This => Ok(scope.get("this".to_string())),
+        Node::Super => Ok(scope.get("super".to_string())),
         Node::FieldAccess(variable, indices) => {
             let value = walk_tree(*variable, scope)?;
-            let fields = indices.iter().map(|i| walk_tree(*i.to_owned(), scope).unwrap_or(Value::Null)).collect::<Vec<Value>>();
+            let fields = indices.iter().map(|i| walk_tree(*i.to_owned(), scope)).collect::<Result<Vec<Value>, Error>>()?;
             let mut field_accessor = FieldAccessor::new(value, fields);
-            Ok(field_accessor.get(scope))
+            field_accessor.get()
         },
         Node::String(value) => Ok(Value::create_string(value, scope)),
+        Node::Integer(value) => Ok(Value::Integer(value)),
         Node::Number(value) => Ok(Value::Number(value)),
         Node::Bool(value) => Ok(Value::Boolean(value)),
         Node::Array(value) => {
@@ -129,8 +188,8 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
             Value::Object(
                 map
                 .into_iter()
-                .map(|x| (x.0, Box::new(walk_tree(*x.1, scope).unwrap())))
-                .collect::<BTreeMap<String, Box<Value>>>()
+                .map(|x| Ok((x.0, Box::new(walk_tree(*x.1, scope)?))))
+                .collect::<Result<BTreeMap<String, Box<Value>>, Error>>()?
             )
         ),
         Node::Ternary(node, true_cond, false_cond) => {
@@ -143,103 +202,124 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
             walk_tree(*false_cond, scope)
         }
         Node::Logical(operator, node1, node2) => {
-            let val1 = walk_tree(*node1, scope);
-            let val2 = walk_tree(*node2, scope);
+            let val1 = walk_tree(*node1, scope)?;
 
-            let ord = val1.clone()?.compare(val2.clone()?);
-            
+            // the right side of && and || is only evaluated when it decides the result
             match operator {
-                LogicalOp::AND => Ok(Value::Boolean(val1?.as_bool() && val2?.as_bool())),
-                LogicalOp::OR => Ok(Value::Boolean(val1?.as_bool() || val2?.as_bool())),
-                LogicalOp::EQ => Ok(Value::Boolean(ord.is_eq())),
-                LogicalOp::NOTEQ => Ok(Value::Boolean(ord.is_ne())),
-                LogicalOp::GT => Ok(Value::Boolean(ord == Ordering::Greater)),
-                LogicalOp::GTEQ => Ok(Value::Boolean(ord == Ordering::Greater || ord == Ordering::Equal)),
-                LogicalOp::LT => Ok(Value::Boolean(ord == Ordering::Less)),
-                LogicalOp::LTEQ => Ok(Value::Boolean(ord == Ordering::Less || ord == Ordering::Equal))
+                LogicalOp::AND => Ok(Value::Boolean(val1.as_bool() && walk_tree(*node2, scope)?.as_bool())),
+                LogicalOp::OR => Ok(Value::Boolean(val1.as_bool() || walk_tree(*node2, scope)?.as_bool())),
+                LogicalOp::EQ => Ok(Value::Boolean(val1.equals(&walk_tree(*node2, scope)?))),
+                LogicalOp::NOTEQ => Ok(Value::Boolean(!val1.equals(&walk_tree(*node2, scope)?))),
+                LogicalOp::GT => Ok(Value::Boolean(order(&val1, &walk_tree(*node2, scope)?) == Some(Ordering::Greater))),
+                LogicalOp::GTEQ => Ok(Value::Boolean(matches!(order(&val1, &walk_tree(*node2, scope)?), Some(Ordering::Greater | Ordering::Equal)))),
+                LogicalOp::LT => Ok(Value::Boolean(order(&val1, &walk_tree(*node2, scope)?) == Some(Ordering::Less))),
+                LogicalOp::LTEQ => Ok(Value::Boolean(matches!(order(&val1, &walk_tree(*node2, scope)?), Some(Ordering::Less | Ordering::Equal))))
             }
         },
         Node::Binary(operator, node1, node2) => {
             let val1 = walk_tree(*node1, scope)?;
             let val2 = walk_tree(*node2, scope)?;
+
+            if let Some(result) = integer_binary(&operator, &val1, &val2) {
+                return Ok(result)
+            }
             
             match operator {
                 BinaryOp::PLUS => {
                     match val1.clone() {
                         Value::String(val) => Ok(Value::String(val + &val2.as_string())),
                         Value::Number(val) => Ok(Value::Number(val + val2.as_number())),
+                        Value::Integer(_val) => Ok(Value::Number(val1.as_number() + val2.as_number())),
                         Value::Array(_values) => Ok(Value::String(val1.as_string() + &val2.as_string())),
                         Value::Boolean(_val) => Ok(Value::Number(val1.as_number() + val2.as_number())),
                         Value::Function(_n, _a, _b) => Ok(Value::String(val1.as_string() + &val2.as_string())),
                         // FIXME: object + number = string
                         Value::Object(_map) => Ok(Value::String(val1.as_string() + &val2.as_string())),
                         Value::Null => Ok(val2),
-                        Value::Class(_n, _p, _c) => Ok(Value::String(val1.as_string() + &val2.as_string()))
+                        Value::Class(_n, _p, _c, _s) => Ok(Value::String(val1.as_string() + &val2.as_string())),
+                        Value::Instance(_n, _f) => Ok(Value::String(val1.as_string() + &val2.as_string()))
                     }
                 },
                 BinaryOp::MINUS => {
                     match val1.clone() {
                         Value::String(_val) => Ok(Value::Number(f64::NAN)),
                         Value::Number(val) => Ok(Value::Number(val - val2.as_number())),
+                        Value::Integer(_val) => Ok(Value::Number(val1.as_number() - val2.as_number())),
                         Value::Array(_values) => Ok(Value::Number(f64::NAN)),
                         Value::Boolean(_val) => Ok(Value::Number(val1.as_number() - val2.as_number())),
                         Value::Function(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
                         Value::Object(_map) => Ok(Value::Number(f64::NAN)),
                         Value::Null => Ok(Value::Number(-&val2.as_number())),
-                        Value::Class(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
+                        Value::Class(_n, _a, _b, _s) => Ok(Value::Number(f64::NAN)),
+                        Value::Instance(_n, _f) => Ok(Value::Number(f64::NAN)),
                     }
                 },
                 BinaryOp::MULTIPLY => {
                     match val1.clone() {
                         Value::String(val) => Ok(Value::String(val.repeat(val2.as_number() as usize))),
                         Value::Number(val) => Ok(Value::Number(val * val2.as_number())),
+                        Value::Integer(_val) => Ok(Value::Number(val1.as_number() * val2.as_number())),
                         Value::Array(_values) => Ok(Value::Number(f64::NAN)),
                         Value::Boolean(_val) => Ok(Value::Number(val1.as_number() * val2.as_number())),
                         Value::Function(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
                         Value::Object(_map) => Ok(Value::Number(f64::NAN)),
                         Value::Null => Ok(Value::Number(0.0)),
-                        Value::Class(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
+                        Value::Class(_n, _a, _b, _s) => Ok(Value::Number(f64::NAN)),
+                        Value::Instance(_n, _f) => Ok(Value::Number(f64::NAN)),
                     }
                 },
                 BinaryOp::DIVIDE => {
                     match val1.clone() {
                         Value::String(_val) => Ok(Value::Number(val1.as_number() / val2.as_number())),
                         Value::Number(val) => Ok(Value::Number(val / val2.as_number())),
+                        Value::Integer(_val) => Ok(Value::Number(val1.as_number() / val2.as_number())),
                         Value::Array(_values) => Ok(Value::Number(f64::NAN)),
                         Value::Boolean(_val) => Ok(Value::Number(val1.as_number() / val2.as_number())),
                         Value::Function(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
                         Value::Object(_map) => Ok(Value::Number(f64::NAN)),
                         Value::Null => Ok(Value::Number(0.0)),
-                        Value::Class(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
+                        Value::Class(_n, _a, _b, _s) => Ok(Value::Number(f64::NAN)),
+                        Value::Instance(_n, _f) => Ok(Value::Number(f64::NAN)),
                     }
                 },
                 BinaryOp::REMAINDER => {
                     match val1.clone() {
                         Value::String(_val) => Ok(Value::Number(val1.as_number() % val2.as_number())),
                         Value::Number(val) => Ok(Value::Number(val % val2.as_number())),
+                        Value::Integer(_val) => Ok(Value::Number(val1.as_number() % val2.as_number())),
                         Value::Array(_values) => Ok(Value::Number(f64::NAN)),
                         Value::Boolean(_val) => Ok(Value::Number(val1.as_number() % val2.as_number())),
                         Value::Function(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
                         Value::Object(_map) => Ok(Value::Number(f64::NAN)),
                         Value::Null => Ok(Value::Number(0.0)),
-                        Value::Class(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
+                        Value::Class(_n, _a, _b, _s) => Ok(Value::Number(f64::NAN)),
+                        Value::Instance(_n, _f) => Ok(Value::Number(f64::NAN)),
                     }
                 },
                 BinaryOp::EXPONENT => {
                     match val1.clone() {
                         Value::String(_val) => Ok(Value::Number(val1.as_number().powf(val2.as_number()))),
                         Value::Number(val) => Ok(Value::Number(val.powf(val2.as_number()))),
+                        Value::Integer(_val) => Ok(Value::Number(val1.as_number().powf(val2.as_number()))),
                         Value::Array(_values) => Ok(Value::Number(f64::NAN)),
                         Value::Boolean(_val) => Ok(Value::Number(val1.as_number().powf(val2.as_number()))),
                         Value::Function(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
                         Value::Object(_map) => Ok(Value::Number(f64::NAN)),
                         Value::Null => Ok(Value::Number(0.0)),
-                        Value::Class(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
+                        Value::Class(_n, _a, _b, _s) => Ok(Value::Number(f64::NAN)),
+                        Value::Instance(_n, _f) => Ok(Value::Number(f64::NAN)),
                     }
                 }
             }
         },
         Node::Unary(operator, node) => {
+            // names that were never assigned are undefined, while explicit nulls stay null
+            if let (UnaryOp::TYPEOF, Node::Var(name)) = (&operator, node.as_ref()) {
+                if !scope.is_defined(name.to_owned()) {
+                    return Ok(Value::String("undefined".to_string()))
+                }
+            }
+
             let value = walk_tree(*node, scope)?;
 
             match operator {
@@ -247,147 +327,113 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
                     match value.clone() {
                         Value::String(_val) => Ok(Value::Number(-value.as_number())),
                         Value::Number(val) => Ok(Value::Number(-val)),
+                        Value::Integer(val) => Ok(val.checked_neg().map(Value::Integer).unwrap_or(Value::Number(-(val as f64)))),
                         Value::Array(_values) => Ok(Value::Number(f64::NAN)),
                         Value::Boolean(_val) => Ok(Value::Number(-value.as_number())),
                         Value::Function(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
                         Value::Object(_map) => Ok(Value::Number(f64::NAN)),
                         Value::Null => Ok(Value::Number(-0.0)),
-                        Value::Class(_n, _a, _b) => Ok(Value::Number(f64::NAN)),
+                        Value::Class(_n, _a, _b, _s) => Ok(Value::Number(f64::NAN)),
+                        Value::Instance(_n, _f) => Ok(Value::Number(f64::NAN)),
                     }
                 },
                 UnaryOp::NOT => {
                     Ok(Value::Boolean(!value.as_bool()))
+                },
+                UnaryOp::TYPEOF => {
+                    Ok(Value::String(value.type_name().to_string()))
                 }
             }
         },
         Node::Fun(variable, args, block) => {
             if let Node::Var(name) = *variable {
-                return Ok(scope.set(
-                    name.clone(), 
-                    Value::Function(name, args, FuncImpl::FromNode(*block))
-                ))
+                let function = Value::Function(name.clone(), args, FuncImpl::FromNode(*block, Rc::new(scope.to_owned())));
+
+                return Ok(scope.set(name, function))
             }
 
-            Ok(Value::Null)
+            // anonymous functions are values
+            Ok(function_value(Node::Fun(variable, args, block), scope))
         },
-        // TODO class and new Class()
-        Node::Class(name, constructor, prototype) => {
-            println!("{:#?}", name);
-            
-            let prot = prototype.iter().fold(BTreeMap::default(), |mut acc, val| {
-                let fun = walk_tree(val.1.to_owned(), scope).unwrap();
+        Node::Class(name, constructor, prototype, parent) => {
+            let parent_class = match parent {
+                Some(parent_node) => match walk_tree(*parent_node, scope)? {
+                    class @ Value::Class(..) => Some(Box::new(class)),
+                    _ => return Err(Error::runtime(RuntimeError::CannotExtend, format!("{name} can only extend a class")))
+                },
+                None => None
+            };
 
-                acc.insert(val.0.to_owned(), Box::new(fun));
+            // methods missing on the child fall back to the parent's prototype
+            let inherited = match parent_class.as_deref() {
+                Some(Value::Class(_, _, parent_prototype, _)) => parent_prototype.to_owned(),
+                _ => BTreeMap::default()
+            };
+
+            let prot = prototype.into_iter().fold(inherited, |mut acc, val| {
+                acc.insert(val.0, Box::new(function_value(val.1, scope)));
 
                 acc
             });
 
-            let cons: Option<Box<Value>> = constructor.map(|c| Box::new(walk_tree(*c, scope).unwrap()));
+            let cons: Option<Box<Value>> = constructor.map(|c| Box::new(function_value(*c, scope)));
 
             // fixme
-            Ok(scope.set(name.clone(), Value::Class(name, cons, prot)))
+            Ok(scope.set(name.clone(), Value::Class(name, cons, prot, parent_class)))
         },
-        Node::FunCall(variable, args) => {
-            let value = walk_tree(*variable.clone(), scope)?;
-            let mut args_eval = args.iter()
-            .map(|arg| walk_tree(*arg.to_owned(), scope).unwrap())
-            .collect::<Vec<Value>>();
-
-            match value {
-                Value::Function(_, mut fun_args, fun_block) => {
-                    let reduced_args = fun_args.reduce(&mut args_eval);
-
-                    match fun_block {
-                        FuncImpl::FromNode(block) => {
-                            let mut fun_scope = Scope::from(Some(Box::new(scope.to_owned())), scope.filename.clone());
-
-                            for arg in reduced_args {
-                                fun_scope.set(arg.0, arg.1);
-                            }
-
-                            walk_tree(block, &mut fun_scope)
-                        },
-                        FuncImpl::Builtin(f) => {
+        // errors without a position of their own point at the call
+        Node::FunCall(variable, args, pos) => fun_call(variable, &args, scope).map_err(|e| e.at(&pos)),
+        Node::SwitchStatement(variable, switch_cases) => {
+            let value = walk_tree(*variable, scope)?;
 
-                            Ok(f(reduced_args))
-                        }
-                    }
-                    
-                },
-                _ => {
-                    match *variable {
-                        Node::Var(name) => {
-                            scope.throw_exception(format!("{name} is not a function"), vec![0, 0]);
-                            return Err(Error { msg: "".to_string(), pos: vec![] })
-                        },
-                        Node::FieldAccess(var, _) => {
-                            if let Node::Var(name) = *var {
-                                scope.throw_exception(format!("{name} is not a function"), vec![0, 0]);
-                                return Err(Error { msg: "".to_string(), pos: vec![] })
-                            }
-                        },
-                        _ => {}
+            // the first case equal to the value is picked, default when none is.
+            // cases without a statement share the statement of the case after them
+            let mut picked = None;
+            for (i, case) in switch_cases.iter().enumerate() {
+                if let SwitchCase::Case(label, _statement) = case {
+                    if walk_tree(label.to_owned(), scope)?.equals(&value) {
+                        picked = Some(i);
+                        break
                     }
-
-                    scope.throw_exception("undefined is not a function".to_string(), vec![0, 0]);
-                    Err(Error { msg: "".to_string(), pos: vec![] })
                 }
             }
-        },
-        Node::SwitchStatement(variable, switch_cases) => {
-            let value = walk_tree(*variable, scope);
 
-            let mut iter = switch_cases.iter();
+            let picked = picked.or_else(|| switch_cases.iter().position(|case| matches!(case, SwitchCase::Default(_))));
 
-            loop {
-                let case = iter.next();
-                match case.unwrap() {
-                    SwitchCase::Case(val, statement) => {
-                        if statement.is_none() {
-                            loop {
-                                let next_case = iter.next();
-                                match next_case.unwrap() {
-                                    SwitchCase::Default(next_default_statement) => {
-                                        let next_default_statement_value = walk_tree(next_default_statement.to_owned(), scope);
-
-                                        //println!("{:#?}", next_default_statement);
-
-                                        return next_default_statement_value;
-                                    },
-                                    SwitchCase::Case(next_val, next_statement) => {
-                                        if next_statement.is_none() {
-                                            continue;
-                                        }
-
-                                        let next_val_value = walk_tree(next_val.to_owned(), scope);
-                                        let next_statement_value = walk_tree(next_statement.to_owned().unwrap(), scope);
-
-                                        if next_val_value == value {
-                                            return next_statement_value
-                                        }
-
-                                        continue;
-                                    }
-                                }
-                            } 
-                        }
+            if let Some(start) = picked {
+                for case in &switch_cases[start..] {
+                    match case {
+                        SwitchCase::Case(_label, Some(statement)) => return walk_tree(statement.to_owned(), scope),
+                        SwitchCase::Default(statement) => return walk_tree(statement.to_owned(), scope),
+                        SwitchCase::Case(_label, None) => {}
+                    }
+                }
+            }
 
-                        let node_val = walk_tree(val.to_owned(), scope);
-                        let statement_value = walk_tree(statement.to_owned().unwrap(), scope);
-                        if node_val == value {
-                            return statement_value
-                        }
+            // nothing matched and there is no default
+            Ok(Value::Null)
+        },
+        // only values raised by `throw` are caught, other errors still end the script.
+        // the caught value is only bound inside the handler
+        Node::TryCatch(block, name, handler) => {
+            match walk_tree(*block, scope) {
+                Err(Error { thrown: Some(value), .. }) => {
+                    let mut handler_scope = scope.block();
+                    handler_scope.set(name, *value);
 
-                        continue;
-                    },
-                    SwitchCase::Default(statement) => {
-                        let statement_value = walk_tree(statement.to_owned(), scope);
+                    let result = walk_tree(*handler, &mut handler_scope);
+                    scope.control = handler_scope.control;
 
-                        return statement_value
-                    }
-                }
+                    result
+                },
+                result => result
             }
         },
+        Node::Throw(value, pos) => {
+            let value = walk_tree(*value, scope)?;
+
+            Err(Error::thrown(value, pos))
+        },
         Node::IfElseStatement(cond, if_node, else_node) => {
             // FIXME: stack?
             if walk_tree(*cond, scope)?.as_bool() {
@@ -400,44 +446,108 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
 
             walk_tree(else_node.unwrap(), scope)
         },
-        Node::WhileStatement(cond, node) => {
+        Node::WhileStatement(cond, node, else_node, collect) => {
+            let mut collected = vec![];
+            let mut completed = true;
+
             while walk_tree(*cond.clone(), scope)?.as_bool() {
-                walk_tree(*node.clone(), scope);
+                let value = loop_body(*node.clone(), scope)?;
+
+                match loop_control(scope) {
+                    Some(Control::Break | Control::Return(_)) => {
+                        completed = false;
+                        break
+                    },
+                    Some(Control::Continue) => continue,
+                    None => collected.push(Box::new(value))
+                }
+            }
+
+            if let (true, Some(else_node)) = (completed, *else_node) {
+                walk_tree(else_node, scope)?;
+            }
+
+            if collect {
+                return Ok(Value::Array(collected))
             }
 
             Ok(Value::Null)
         },
-        Node::ForStatement(variable, iterator, block) => {
-            let iter = walk_tree(*iterator, scope)?;
+        // the body runs once before the condition is checked
+        Node::DoWhileStatement(node, cond) => {
+            loop {
+                loop_body(*node.clone(), scope)?;
 
-            match &iter {
-                Value::String(str) => {
-                    let str_splitted = str
-                        .chars()
-                        .map(|ch| Value::String(ch.to_string()))
-                        .collect::<Vec<Value>>();
+                if let Some(Control::Break | Control::Return(_)) = loop_control(scope) {
+                    break
+                }
 
-                    for value in str_splitted {
-                        scope.set(variable.clone(), value);
-                        walk_tree(*block.clone(), scope);
-                    }
+                if !walk_tree(*cond.clone(), scope)?.as_bool() {
+                    break
+                }
+            }
 
-                    Ok(Value::Null)
-                },
-                Value::Array(values) => {
-                    let values_unboxed = values.iter().map(|val| *val.to_owned()).collect::<Vec<Value>>();
-                    for value in values_unboxed {
-                        scope.set(variable.clone(), value);
-                        walk_tree(*block.clone(), scope);
-                    }
+            Ok(Value::Null)
+        },
+        Node::ForStatement(variable, iterator, block, else_block, collect, pos) => {
+            let iter = walk_tree(*iterator, scope).map_err(|e| e.at(&pos))?;
 
-                    Ok(Value::Null)
-                },
-                _ => {
-                    scope.throw_exception("Value cannot be iterated".to_string(), vec![0, 0]);
-                    Err(Error { msg: "Value cannot be iterated".to_string(), pos: vec![0, 0] })
+            let values = match &iter {
+                Value::String(str) => str
+                    .chars()
+                    .map(|ch| Value::String(ch.to_string()))
+                    .collect::<Vec<Value>>(),
+                Value::Array(values) => values.iter().map(|val| *val.to_owned()).collect::<Vec<Value>>(),
+                // objects are iterated by key, instances by the keys of their fields
+                Value::Object(map) => map.keys().map(|key| Value::String(key.to_owned())).collect::<Vec<Value>>(),
+                Value::Instance(_, fields) => own_fields(fields).into_keys().map(Value::String).collect::<Vec<Value>>(),
+                _ => return Err(Error::runtime(RuntimeError::NotIterable, format!("{} cannot be iterated", iter.type_name())).at(&pos))
+            };
+
+            let mut collected = vec![];
+            let mut completed = true;
+
+            for value in values {
+                scope.set(variable.clone(), value);
+                let result = loop_body(*block.clone(), scope).map_err(|e| e.at(&pos))?;
+
+                match loop_control(scope) {
+                    Some(Control::Break | Control::Return(_)) => {
+                        completed = false;
+                        break
+                    },
+                    Some(Control::Continue) => continue,
+                    None => collected.push(Box::new(result))
                 }
             }
+
+            if let (true, Some(else_block)) = (completed, *else_block) {
+                walk_tree(else_block, scope)?;
+            }
+
+            if collect {
+                return Ok(Value::Array(collected))
+            }
+
+            Ok(Value::Null)
+        },
+        Node::Return(value) => {
+            let value = walk_tree(*value, scope)?;
+            scope.control = Some(Control::Return(value.clone()));
+            Ok(value)
+        },
+        Node::Break => {
+            scope.control = Some(Control::Break);
+            Ok(Value::Null)
+        },
+        Node::Continue => {
+            scope.control = Some(Control::Continue);
+            Ok(Value::Null)
+        },
+        Node::Unit(value, unit, pos) => {
+            let value = walk_tree(*value, scope)?;
+
+            units::convert(value.as_number(), &unit).map_err(|e| e.at(&pos))
         },
         Node::Range(from, to, inclusive) => {
             let from_value = walk_tree(*from, scope)?.as_number() as u64;
@@ -450,9 +560,698 @@ pub fn walk_tree(node: Node, scope: &mut Scope) -> Result<Value, Error> {
             }
 
             Ok(Value::Array(
-                range.iter().map(|v| Box::new(Value::Number(*v as f64))).collect()
+                range.iter().map(|v| Box::new(Value::Integer(*v as i64))).collect()
             ))
         },
         _ => Ok(Value::Null)
     }
-}
\ No newline at end of file
+}
+
+fn function_value(node: Node, scope: &Scope) -> Value {
+    match node {
+        Node::Fun(variable, args, block) => {
+            let name = match *variable {
+                Node::Var(name) => name,
+                _ => "anonymous".to_string()
+            };
+
+            Value::Function(name, args, FuncImpl::FromNode(*block, Rc::new(scope.to_owned())))
+        },
+        _ => Value::Null
+    }
+}
+
+fn fun_call(variable: Box<Node>, args: &[Box<Node>], scope: &mut Scope) -> Result<Value, Error> {
+    // methods are looked up on a receiver, which becomes `this` inside the call
+    let mut receiver = None;
+    let mut method = None;
+    let value = match *variable.clone() {
+        Node::FieldAccess(var, mut indices) => {
+            let last = indices.pop().unwrap();
+            let target = if indices.is_empty() { *var } else { Node::FieldAccess(var, indices) };
+            let target_value = walk_tree(target.clone(), scope)?;
+            let field = walk_tree(*last, scope)?;
+
+            if let Value::String(name) = &field {
+                if methods::has_method(&target_value, name) {
+                    method = Some(name.to_owned());
+                }
+            }
+
+            let fun = match method {
+                Some(_) => Value::Null,
+                None => FieldAccessor::new(target_value.clone(), vec![field]).get()?
+            };
+
+            receiver = Some((target, target_value));
+
+            fun
+        },
+        _ => walk_tree(*variable.clone(), scope)?
+    };
+    let args_eval = args.iter()
+    .map(|arg| walk_tree(*arg.to_owned(), scope))
+    .collect::<Result<Vec<Value>, Error>>()?;
+
+    if let (Some(name), Some((target, target_value))) = (method, receiver.clone()) {
+        let mut updated = target_value.clone();
+        let result = methods::call_method(&mut updated, &name, args_eval, scope)?;
+
+        // methods that change the value in place write it back to where it came from
+        if updated != target_value {
+            match target {
+                Node::Var(name) => {
+                    scope.write_back(name, updated);
+                },
+                target => assign(target, updated, scope)?
+            }
+        }
+
+        return Ok(result)
+    }
+
+    match value {
+        Value::Function(..) => {
+            let this = match &receiver {
+                Some((_, this @ (Value::Object(_) | Value::Instance(..)))) => Some(this.to_owned()),
+                _ => None
+            };
+
+            let (result, updated) = invoke(value, args_eval, this.clone(), scope)?;
+
+            // write back fields the method changed on `this`
+            if let (Some((target, _)), Some(this), Some(updated)) = (receiver, this, updated) {
+                if updated != this {
+                    assign(target, updated, scope)?;
+                }
+            }
+
+            Ok(result)
+        },
+        class @ Value::Class(..) => {
+            // super(...) runs the parent constructor on the current instance
+            if let Node::Super = *variable {
+                let this = scope.get("this".to_string());
+                let this = construct(class, this, args_eval, scope)?;
+                scope.set("this".to_string(), this);
+
+                return Ok(Value::Null)
+            }
+
+            call_function(class, args_eval, scope)
+        },
+        _ => {
+            let name = match *variable {
+                Node::Var(name) => name,
+                Node::FieldAccess(var, _) => match *var {
+                    Node::Var(name) => name,
+                    _ => "undefined".to_string()
+                },
+                _ => "undefined".to_string()
+            };
+
+            Err(Error::runtime(RuntimeError::NotAFunction, format!("{name} is not a function")))
+        }
+    }
+}
+
+pub fn call_function(function: Value, args: Vec<Value>, scope: &mut Scope) -> Result<Value, Error> {
+    Ok(invoke(function, args, None, scope)?.0)
+}
+
+// calls a function or class value, binding `this` for method calls.
+// returns the result along with `this` as the call left it
+fn invoke(function: Value, mut args: Vec<Value>, this: Option<Value>, scope: &mut Scope) -> Result<(Value, Option<Value>), Error> {
+    match function {
+        Value::Function(_, mut fun_args, fun_block) => {
+            let reduced_args = fun_args.reduce(&mut args, scope)?;
+
+            match fun_block {
+                FuncImpl::FromNode(block, env) => {
+                    let mut fun_scope = scope.call(env)?;
+
+                    for arg in reduced_args {
+                        fun_scope.set(arg.0, arg.1);
+                    }
+
+                    let bound = this.is_some();
+                    if let Some(this) = this {
+                        fun_scope.set("this".to_string(), this);
+                    }
+
+                    let mut result = walk_tree(block, &mut fun_scope)?;
+                    if let Some(Control::Return(value)) = fun_scope.control.take() {
+                        result = value;
+                    }
+
+                    let updated = match bound {
+                        true => Some(fun_scope.get("this".to_string())),
+                        false => None
+                    };
+
+                    Ok((result, updated))
+                },
+                FuncImpl::Builtin(f) => Ok((f(reduced_args)?, None)),
+                FuncImpl::Callback(f) => Ok((f(reduced_args, scope)?, None))
+            }
+        },
+        Value::Class(ref name, _, ref prototype, _) => {
+            let instance = Value::Instance(name.to_owned(), prototype.to_owned());
+
+            Ok((construct(function, instance, args, scope)?, None))
+        },
+        _ => Err(Error::runtime(RuntimeError::NotAFunction, format!("{} is not a function", function.as_string())))
+    }
+}
+
+// NaN is unordered, so it never compares to anything
+fn order(val1: &Value, val2: &Value) -> Option<Ordering> {
+    match (val1, val2) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Integer(_) | Value::Number(_), _) => val1.as_number().partial_cmp(&val2.as_number()),
+        (val, _) => Some(val.compare(val2.clone()))
+    }
+}
+
+// arithmetic on two integers, None when the result isn't a whole number
+// or doesn't fit, the operation is done on floats then
+fn integer_binary(operator: &BinaryOp, val1: &Value, val2: &Value) -> Option<Value> {
+    let (a, b) = match (val1, val2) {
+        (Value::Integer(a), Value::Integer(b)) => (*a, *b),
+        _ => return None
+    };
+
+    let result = match operator {
+        BinaryOp::PLUS => a.checked_add(b),
+        BinaryOp::MINUS => a.checked_sub(b),
+        BinaryOp::MULTIPLY => a.checked_mul(b),
+        BinaryOp::DIVIDE => match a.checked_rem(b) {
+            Some(0) => a.checked_div(b),
+            _ => None
+        },
+        BinaryOp::REMAINDER => a.checked_rem(b),
+        BinaryOp::EXPONENT => u32::try_from(b).ok().and_then(|b| a.checked_pow(b))
+    };
+
+    result.map(Value::Integer)
+}
+
+// takes a break/continue signal for the loop, a return stays set
+// so that it reaches the enclosing function
+fn loop_control(scope: &mut Scope) -> Option<Control> {
+    match scope.control {
+        Some(Control::Return(_)) => scope.control.clone(),
+        _ => scope.control.take()
+    }
+}
+
+// runs a loop body, evaluating to the value of its last statement
+fn loop_body(body: Node, scope: &mut Scope) -> Result<Value, Error> {
+    match body {
+        Node::BlockStatement(statements) => {
+            let mut result = Value::Null;
+
+            for statement in statements {
+                result = walk_tree(*statement, scope)?;
+
+                if scope.control.is_some() {
+                    break;
+                }
+            }
+
+            Ok(result)
+        },
+        _ => walk_tree(body, scope)
+    }
+}
+
+fn construct(class: Value, this: Value, mut args: Vec<Value>, scope: &mut Scope) -> Result<Value, Error> {
+    if let Value::Class(_, constructor, _, parent) = class {
+        match constructor.map(|c| *c) {
+            Some(Value::Function(_, mut cons_args, FuncImpl::FromNode(block, env))) => {
+                let mut cons_scope = scope.call(env)?;
+
+                for arg in cons_args.reduce(&mut args, scope)? {
+                    cons_scope.set(arg.0, arg.1);
+                }
+                cons_scope.set("this".to_string(), this);
+
+                if let Some(parent_class) = parent {
+                    cons_scope.set("super".to_string(), *parent_class);
+                }
+
+                walk_tree(block, &mut cons_scope)?;
+
+                return Ok(cons_scope.get("this".to_string()))
+            },
+            _ => {
+                // without its own constructor, a class passes the arguments to its parent
+                if let Some(parent_class) = parent {
+                    return construct(*parent_class, this, args, scope)
+                }
+            }
+        }
+    }
+
+    Ok(this)
+}
+
+fn assign(variable: Node, value: Value, scope: &mut Scope) -> Result<(), Error> {
+    match variable {
+        Node::Var(name) => {
+            scope.update(name, value);
+        },
+        Node::This => {
+            scope.update("this".to_string(), value);
+        },
+        Node::FieldAccess(var, indices) => {
+            let target = match *var.clone() {
+                Node::Var(name) => Some(name),
+                Node::This => Some("this".to_string()),
+                _ => None
+            };
+
+            if let Some(name) = target {
+                let var_value = walk_tree(*var, scope)?;
+                let fields = indices.iter().map(|i| walk_tree(*i.to_owned(), scope)).collect::<Result<Vec<Value>, Error>>()?;
+                let mut field_accessor = FieldAccessor::new(var_value, fields);
+                let value = field_accessor.set(value)?;
+
+                scope.write_back(name, value);
+            }
+        },
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, ErrorKind, ParseError, RuntimeError};
+
+    use super::types::Value;
+
+    fn ints(values: &[i64]) -> Value {
+        Value::Array(values.iter().map(|value| Box::new(Value::Integer(*value))).collect())
+    }
+
+    #[test]
+    fn loops_collect_iteration_values() {
+        let scope = eval("
+            let squares = for (x in 0..4) { x * x }
+            let i = 0
+            let counted = while (i < 3) { i += 1
+                i }
+        ").unwrap();
+
+        assert_eq!(scope.get("squares".to_string()), ints(&[0, 1, 4, 9]));
+        assert_eq!(scope.get("counted".to_string()), ints(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn continue_skips_the_iteration_value() {
+        let scope = eval("
+            let odd = for (x in 0..6) {
+                if (x % 2 == 0) { continue }
+                x
+            }
+        ").unwrap();
+
+        assert_eq!(scope.get("odd".to_string()), ints(&[1, 3, 5]));
+    }
+
+    #[test]
+    fn break_stops_collecting() {
+        let scope = eval("
+            let firsts = for (x in 0..10) {
+                if (x == 3) { break }
+                x
+            }
+            let after = 'reached'
+        ").unwrap();
+
+        assert_eq!(scope.get("firsts".to_string()), ints(&[0, 1, 2]));
+        assert_eq!(scope.get("after".to_string()), Value::String("reached".to_string()));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let scope = eval("
+            let calls = 0
+            fun touch(result) {
+                global calls = calls + 1
+                return result
+            }
+            let a = false && touch(true)
+            let b = true || touch(false)
+            let c = true && touch(false)
+            let d = false || touch(true)
+        ").unwrap();
+
+        assert_eq!(scope.get("a".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("b".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("c".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("d".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("calls".to_string()), Value::Integer(2));
+    }
+
+    #[test]
+    fn skipped_right_side_is_never_run() {
+        assert!(eval("let x = false && undefinedFunction()").is_ok());
+        assert!(eval("let x = true && undefinedFunction()").is_err());
+    }
+
+    #[test]
+    fn postfix_increment_in_a_condition() {
+        let scope = eval("
+            let i = 0
+            let runs = 0
+            while (i++ < 3) { runs += 1 }
+            let j = 5
+            let sum = j-- + 1
+        ").unwrap();
+
+        assert_eq!(scope.get("runs".to_string()), Value::Integer(3));
+        assert_eq!(scope.get("i".to_string()), Value::Integer(4));
+        assert_eq!(scope.get("sum".to_string()), Value::Integer(6));
+        assert_eq!(scope.get("j".to_string()), Value::Integer(4));
+    }
+
+    #[test]
+    fn large_integers_stay_exact() {
+        let scope = eval("
+            let a = 9007199254740993 + 0
+            let b = 10000000000000000 + 1
+            let c = 3037000499 * 3037000499
+            let d = 9223372036854775807 + 1
+            let e = 7 / 2
+            let f = 2 ** 62
+        ").unwrap();
+
+        assert_eq!(scope.get("a".to_string()), Value::Integer(9007199254740993));
+        assert_eq!(scope.get("b".to_string()), Value::Integer(10000000000000001));
+        assert_eq!(scope.get("c".to_string()), Value::Integer(9223372030926249001));
+        // past i64 the arithmetic goes on in floats
+        assert_eq!(scope.get("d".to_string()), Value::Number(9223372036854775808.0));
+        assert_eq!(scope.get("e".to_string()), Value::Number(3.5));
+        assert_eq!(scope.get("f".to_string()), Value::Integer(1 << 62));
+    }
+
+    #[test]
+    fn integer_indexes_are_exact() {
+        let scope = eval("
+            let arr = [10, 20, 30]
+            let computed = arr[6 / 3 - 1]
+            let last = arr[-1]
+            let past = arr[3]
+            let truncated = arr[1.5]
+        ").unwrap();
+
+        assert_eq!(scope.get("computed".to_string()), Value::Integer(20));
+        assert_eq!(scope.get("last".to_string()), Value::Integer(30));
+        assert_eq!(scope.get("past".to_string()), Value::Null);
+        assert_eq!(scope.get("truncated".to_string()), Value::Integer(20));
+        assert!(eval("let arr = [10, 20, 30]\nlet x = arr[-4]").is_err());
+        assert!(eval("let arr = [10, 20, 30]\nlet x = arr[9223372036854775807 * 2]").is_err());
+    }
+
+    #[test]
+    fn array_patterns_fill_missing_elements_with_null() {
+        let scope = eval("let [a, b, c] = [1, 2]").unwrap();
+
+        assert_eq!(scope.get("a".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("b".to_string()), Value::Integer(2));
+        assert_eq!(scope.get("c".to_string()), Value::Null);
+    }
+
+    #[test]
+    fn array_rest_takes_the_remaining_elements() {
+        let scope = eval("
+            let [head, ...tail] = [1, 2, 3]
+            let [only, ...none] = [1]
+        ").unwrap();
+
+        assert_eq!(scope.get("head".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("tail".to_string()), ints(&[2, 3]));
+        assert_eq!(scope.get("none".to_string()), ints(&[]));
+    }
+
+    #[test]
+    fn object_patterns_bind_fields_by_name() {
+        let scope = eval("let {x, y, z} = {x: 1, y: 2}").unwrap();
+
+        assert_eq!(scope.get("x".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("y".to_string()), Value::Integer(2));
+        assert_eq!(scope.get("z".to_string()), Value::Null);
+    }
+
+    #[test]
+    fn object_rest_takes_the_other_fields() {
+        let scope = eval("
+            let {name, ...others} = {name: 'coco', age: 3, kind: 'cat'}
+            let keys = others.keys()
+        ").unwrap();
+
+        assert_eq!(scope.get("name".to_string()), Value::String("coco".to_string()));
+        assert_eq!(scope.get("keys".to_string()), Value::Array(vec![
+            Box::new(Value::String("age".to_string())),
+            Box::new(Value::String("kind".to_string()))
+        ]));
+    }
+
+    #[test]
+    fn patterns_need_a_matching_value() {
+        let kind = |code: &str| eval(code).err().map(|e| e.kind());
+
+        assert_eq!(kind("let [a, ...rest, b] = [1, 2, 3]"), Some(ErrorKind::Parse(ParseError::InvalidPattern)));
+        assert_eq!(kind("let [a, b] = 5"), Some(ErrorKind::Runtime(RuntimeError::CannotDestructure)));
+        assert_eq!(kind("let {a} = [1, 2]"), Some(ErrorKind::Runtime(RuntimeError::CannotDestructure)));
+    }
+
+    #[test]
+    fn thrown_errors_are_caught_and_execution_continues() {
+        let scope = eval("
+            fun divide(a, b) {
+                if (b == 0) throw 'division by zero'
+                return a / b
+            }
+            let result = null
+            let caught = null
+            try {
+                result = divide(1, 0)
+            } catch (e) {
+                caught = e
+            }
+            let after = divide(6, 3)
+        ").unwrap();
+
+        assert_eq!(scope.get("result".to_string()), Value::Null);
+        assert_eq!(scope.get("caught".to_string()), Value::String("division by zero".to_string()));
+        assert_eq!(scope.get("after".to_string()), Value::Integer(2));
+    }
+
+    #[test]
+    fn throws_inside_object_literals_are_caught() {
+        let scope = eval("
+            fun boom() { throw 'x' }
+            let caught = null
+            try {
+                let o = {a: boom()}
+            } catch (e) {
+                caught = e
+            }
+        ").unwrap();
+
+        assert_eq!(scope.get("caught".to_string()), Value::String("x".to_string()));
+    }
+
+    #[test]
+    fn the_caught_value_stays_in_the_handler() {
+        let scope = eval("
+            let e = 'outer'
+            try { throw 'inner' } catch (e) { }
+            let seen = null
+            try { throw 1 } catch (err) { seen = err }
+        ").unwrap();
+
+        assert_eq!(scope.get("e".to_string()), Value::String("outer".to_string()));
+        assert!(!scope.is_defined("err".to_string()));
+        assert_eq!(scope.get("seen".to_string()), Value::Integer(1));
+    }
+
+    #[test]
+    fn interpreter_errors_are_not_caught() {
+        assert!(eval("try { let x = notAFunction() } catch (e) { }").is_err());
+    }
+
+    #[test]
+    fn typeof_tells_undeclared_names_from_null() {
+        let scope = eval("
+            let empty = null
+            let undeclared = typeof missing
+            let explicit = typeof empty
+            let number = typeof 1
+        ").unwrap();
+
+        assert_eq!(scope.get("undeclared".to_string()), Value::String("undefined".to_string()));
+        assert_eq!(scope.get("explicit".to_string()), Value::String("null".to_string()));
+        assert_eq!(scope.get("number".to_string()), Value::String("number".to_string()));
+    }
+
+    #[test]
+    fn numbers_display_without_float_noise() {
+        let scope = eval("
+            let whole = str(3.0)
+            let half = str(3.5)
+            let noisy = str(0.1 + 0.2)
+            let large = str(123456789012345680000.0)
+            let huge = str(2.0 ** 70)
+        ").unwrap();
+
+        assert_eq!(scope.get("whole".to_string()), Value::String("3".to_string()));
+        assert_eq!(scope.get("half".to_string()), Value::String("3.5".to_string()));
+        assert_eq!(scope.get("noisy".to_string()), Value::String("0.3".to_string()));
+        assert_eq!(scope.get("large".to_string()), Value::String("123456789012345680000".to_string()));
+        // from 1e21 on the exponent form is shorter
+        assert_eq!(scope.get("huge".to_string()), Value::String("1.18059162071741e21".to_string()));
+    }
+
+    #[test]
+    fn nan_compares_like_ieee() {
+        let scope = eval("
+            let nan = 0 / 0
+            let same = nan == nan
+            let different = nan != nan
+            let less = nan < 1
+            let greater = nan > 1
+            let at_most = nan <= nan
+        ").unwrap();
+
+        assert_eq!(scope.get("same".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("different".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("less".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("greater".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("at_most".to_string()), Value::Boolean(false));
+    }
+
+    #[test]
+    fn nested_containers_compare_deeply() {
+        let scope = eval("
+            let arrays = [1, [2, [3]]] == [1, [2, [3]]]
+            let inner = [1, [2, [3]]] == [1, [2, [4]]]
+            let objects = {a: {b: [1, 2]}, c: 3} == {c: 3, a: {b: [1, 2]}}
+            let extra = {a: 1} == {a: 1, b: 2}
+            let unequal = [{x: 1}] != [{x: 2}]
+        ").unwrap();
+
+        assert_eq!(scope.get("arrays".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("inner".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("objects".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("extra".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("unequal".to_string()), Value::Boolean(true));
+    }
+
+    #[test]
+    fn compound_assignment_writes_back_into_fields() {
+        let scope = eval("
+            let counter = {count: 1, nested: {total: 10}}
+            counter.count += 4
+            counter.nested.total -= 3
+            let count = counter.count
+            let total = counter.nested.total
+            let values = [1, 2, 3]
+            let i = 1
+            values[i] *= 5
+            values[-1] += 1
+        ").unwrap();
+
+        assert_eq!(scope.get("count".to_string()), Value::Integer(5));
+        assert_eq!(scope.get("total".to_string()), Value::Integer(7));
+        assert_eq!(scope.get("values".to_string()), ints(&[1, 10, 4]));
+    }
+
+    #[test]
+    fn a_throw_in_a_for_body_ends_the_loop() {
+        let scope = eval("
+            let seen = []
+            let caught = null
+            try {
+                for (x in [1, 2, 3, 4]) {
+                    if (x == 3) throw 'stop at ' + x
+                    seen.push(x)
+                }
+            } catch (e) {
+                caught = e
+            }
+        ").unwrap();
+
+        assert_eq!(scope.get("seen".to_string()), ints(&[1, 2]));
+        assert_eq!(scope.get("caught".to_string()), Value::String("stop at 3".to_string()));
+        assert!(eval("for (x in [1, 2]) { throw x }\nlet after = true").is_err());
+    }
+
+    #[test]
+    fn do_while_runs_its_body_once_before_checking() {
+        let scope = eval("
+            let runs = 0
+            do { runs += 1 } while (false)
+            let count = 0
+            do { count += 1 } while (count < 3)
+        ").unwrap();
+
+        assert_eq!(scope.get("runs".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("count".to_string()), Value::Integer(3));
+    }
+
+    #[test]
+    fn switch_on_strings_falls_back_to_default() {
+        let scope = eval("
+            fun sound(animal) {
+                let result = 'unknown'
+                switch (animal) {
+                    case 'cat': result = 'meow'
+                    case 'dog': result = 'woof'
+                    default: result = 'silence'
+                }
+                return result
+            }
+            fun purr(animal) {
+                let result = 'unset'
+                switch (animal) {
+                    case 'cat': result = 'purr'
+                }
+                return result
+            }
+            let cat = sound('cat')
+            let dog = sound('dog')
+            let fish = sound('fish')
+            let unmatched = purr('dog')
+        ").unwrap();
+
+        assert_eq!(scope.get("cat".to_string()), Value::String("meow".to_string()));
+        assert_eq!(scope.get("dog".to_string()), Value::String("woof".to_string()));
+        assert_eq!(scope.get("fish".to_string()), Value::String("silence".to_string()));
+        // no case and no default, nothing runs
+        assert_eq!(scope.get("unmatched".to_string()), Value::String("unset".to_string()));
+    }
+
+    #[test]
+    fn switch_on_typeof() {
+        let scope = eval("
+            fun kind(x) {
+                switch (typeof x) {
+                    case 'number': return 'n'
+                    case 'string': return 's'
+                    case 'array': return 'a'
+                    default: return '?'
+                }
+            }
+            let kinds = [kind(1), kind(1.5), kind('x'), kind([1]), kind(null)]
+        ").unwrap();
+
+        assert_eq!(scope.get("kinds".to_string()), Value::Array(
+            ["n", "n", "s", "a", "?"].iter().map(|kind| Box::new(Value::String(kind.to_string()))).collect()
+        ));
+    }
+}
@@ -1,44 +1,113 @@
-use std::{collections::HashMap, process::exit};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use colored::Colorize;
-use lazy_static::lazy_static;
-
-use crate::modules::io;
+use crate::{modules::{io, object, reflect, time}, Error, RuntimeError};
 
 use super::types::{Value, FuncImpl, FunctionArguments, FunctionArgument};
 
-lazy_static! {
-    static ref STD: HashMap<String, Value> = HashMap::from([
+// builtins live here once, scopes fall back to them when a name isn't defined.
+// values hold scopes, which can't be shared between threads
+thread_local! {
+    static STD: HashMap<String, Value> = HashMap::from([
         ("log".to_owned(), io::get_write()),
+        ("print".to_owned(), io::get_print()),
+        ("write".to_owned(), io::get_write_raw()),
         ("num".to_owned(), Value::Function(
             "num".to_owned(),
             FunctionArguments::new(Vec::from([FunctionArgument::Required("any".to_string())])), 
             FuncImpl::Builtin(|vals| {
-                Value::Number(vals.get("any").unwrap().as_number())
+                Ok(Value::Number(vals.get("any").unwrap().as_number()))
             })
         )),
         ("bool".to_owned(), Value::Function(
             "bool".to_owned(),
             FunctionArguments::new(Vec::from([FunctionArgument::Required("any".to_string())])), 
             FuncImpl::Builtin(|vals| {
-                Value::Boolean(vals.get("any").unwrap().as_bool())
+                Ok(Value::Boolean(vals.get("any").unwrap().as_bool()))
             })
         )),
         ("str".to_owned(), Value::Function(
             "str".to_owned(),
             FunctionArguments::new(Vec::from([FunctionArgument::Required("any".to_string())])), 
             FuncImpl::Builtin(|vals| {
-                Value::String(vals.get("any").unwrap().as_string())
+                Ok(Value::String(vals.get("any").unwrap().as_string()))
+            })
+        )),
+        ("rawString".to_owned(), Value::Function(
+            "rawString".to_owned(),
+            FunctionArguments::new(Vec::from([FunctionArgument::Required("any".to_string())])), 
+            FuncImpl::Builtin(|vals| {
+                Ok(Value::String(vals.get("any").unwrap().as_raw_string()))
             })
         )),
+        // the codepoint of the first character, null for ''
+        ("ord".to_owned(), Value::Function(
+            "ord".to_owned(),
+            FunctionArguments::new(Vec::from([FunctionArgument::Required("string".to_string())])),
+            FuncImpl::Builtin(|vals| {
+                let string = vals.get("string").unwrap().as_string();
+
+                Ok(string.chars().next().map(|c| Value::Integer(c as i64)).unwrap_or(Value::Null))
+            })
+        )),
+        ("chr".to_owned(), Value::Function(
+            "chr".to_owned(),
+            FunctionArguments::new(Vec::from([FunctionArgument::Required("codepoint".to_string())])),
+            FuncImpl::Builtin(|vals| {
+                let codepoint = vals.get("codepoint").unwrap().as_number();
+
+                // surrogates and values past 0x10FFFF aren't characters
+                let c = match codepoint.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&codepoint) {
+                    true => char::from_u32(codepoint as u32),
+                    false => None
+                };
+
+                match c {
+                    Some(c) => Ok(Value::String(c.to_string())),
+                    None => Err(Error::runtime(RuntimeError::InvalidArgument, format!("Invalid codepoint: {}", Value::Number(codepoint).as_string())))
+                }
+            })
+        )),
+        ("zipObject".to_owned(), object::get_zip_object()),
+        ("invert".to_owned(), object::get_invert()),
+        ("getPath".to_owned(), object::get_get_path()),
+        ("setPath".to_owned(), object::get_set_path()),
+        ("pick".to_owned(), object::get_pick()),
+        ("omit".to_owned(), object::get_omit()),
+        ("deepEqual".to_owned(), object::get_deep_equal()),
+        ("bench".to_owned(), time::get_bench()),
+        ("flush".to_owned(), io::get_flush()),
+        ("classOf".to_owned(), reflect::get_class_of()),
     ]);
 }
 
+// how deep calls can nest before a runaway recursion is stopped. the calls have
+// to fit in the interpreter's stack (STACK_SIZE), and unoptimized builds use
+// several times bigger frames
+pub const DEFAULT_MAX_DEPTH: usize = if cfg!(debug_assertions) { 100 } else { 1000 };
+
+// signals raised by break/continue, consumed by the enclosing loop,
+// and by return, consumed by the enclosing function
+#[derive(Clone, Debug, PartialEq)]
+pub enum Control {
+    Break,
+    Continue,
+    Return(Value)
+}
+
+// clones of a scope share its variables and its parents, which is how functions
+// keep the scope they were defined in alive. cloning one is cheap, no matter
+// how many variables or parents it has
 #[derive(Clone, Debug)]
 pub struct Scope {
-    previous: Option<Box<Scope>>,
-    variables: HashMap<String, Value>,
-    pub filename: String
+    previous: Option<Rc<Scope>>,
+    variables: Rc<RefCell<HashMap<String, Value>>>,
+    pub control: Option<Control>,
+    pub filename: String,
+    // the number of calls this scope is nested in
+    depth: usize,
+    pub max_depth: usize,
+    // block scopes only keep the names bound in them, assignments pass through
+    block: bool
 }
 
 impl Scope {
@@ -46,49 +115,89 @@ impl Scope {
         Self::from(None, filename)
     }
 
-    pub fn from(previous: Option<Box<Scope>>, filename: String) -> Self {
+    pub fn from(previous: Option<Rc<Scope>>, filename: String) -> Self {
         Self {
             previous,
-            variables: HashMap::from([
-                ("log".to_owned(), io::get_write()),
-                ("num".to_owned(), Value::Function(
-                    "num".to_owned(),
-                    FunctionArguments::new(Vec::from([FunctionArgument::Required("any".to_string())])), 
-                    FuncImpl::Builtin(|vals| {
-                        Value::Number(vals.get("any").unwrap().as_number())
-                    })
-                )),
-                ("bool".to_owned(), Value::Function(
-                    "bool".to_owned(),
-                    FunctionArguments::new(Vec::from([FunctionArgument::Required("any".to_string())])), 
-                    FuncImpl::Builtin(|vals| {
-                        Value::Boolean(vals.get("any").unwrap().as_bool())
-                    })
-                )),
-                ("str".to_owned(), Value::Function(
-                    "str".to_owned(),
-                    FunctionArguments::new(Vec::from([FunctionArgument::Required("any".to_string())])), 
-                    FuncImpl::Builtin(|vals| {
-                        Value::String(vals.get("any").unwrap().as_string())
-                    })
-                )),
-            ]),
-            filename
+            variables: Rc::new(RefCell::new(HashMap::new())),
+            control: None,
+            filename,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            block: false
+        }
+    }
+
+    // the scope of a call made from this one, on top of the scope the function was defined in
+    pub fn call(&self, env: Rc<Scope>) -> Result<Scope, Error> {
+        if self.depth >= self.max_depth {
+            return Err(Error::runtime(RuntimeError::StackOverflow, format!("Maximum call stack exceeded ({} calls)", self.max_depth)))
         }
+
+        let mut scope = Scope::from(Some(env), self.filename.clone());
+        scope.depth = self.depth + 1;
+        scope.max_depth = self.max_depth;
+
+        Ok(scope)
+    }
+
+    // the scope of a block run inside this one, like a catch handler
+    pub fn block(&self) -> Scope {
+        let mut scope = Scope::from(Some(Rc::new(self.clone())), self.filename.clone());
+        scope.depth = self.depth;
+        scope.max_depth = self.max_depth;
+        scope.block = true;
+
+        scope
     }
 
-    pub fn get(&self, name: String) -> &Value {
+    pub fn get(&self, name: String) -> Value {
         let scope = self.find_scope(name.clone());
         
-        scope.variables.get(&name).unwrap_or(&Value::Null)
+        scope.variables.borrow().get(&name).cloned()
+            .or_else(|| STD.with(|std| std.get(&name).cloned()))
+            .unwrap_or(Value::Null)
     }
 
-    pub fn set(&mut self, name: String, value: Value) -> Value {
-        self.variables.insert(name, value).unwrap_or(Value::Null)
+    pub fn set(&self, name: String, value: Value) -> Value {
+        self.variables.borrow_mut().insert(name, value).unwrap_or(Value::Null)
+    }
+
+    // assignments change a variable where it is defined, so closures can update
+    // the variables they captured. globals are only written through `global`,
+    // anything else becomes a local
+    pub fn update(&self, name: String, value: Value) -> Value {
+        if let (true, Some(previous)) = (self.block && !self.is_present(name.clone()), &self.previous) {
+            return previous.update(name, value)
+        }
+
+        let mut scope: &Scope = self;
+        while let Some(previous) = &scope.previous {
+            if scope.is_present(name.clone()) {
+                return scope.variables.borrow_mut().insert(name, value).unwrap_or(Value::Null)
+            }
+            scope = previous
+        }
+
+        self.set(name, value)
+    }
+
+    // a value changed in place (a field set, a push) goes back to where the
+    // variable is defined, globals included. only rebinding a global needs `global`
+    pub fn write_back(&self, name: String, value: Value) -> Value {
+        let scope = self.find_scope(name.clone());
+
+        match scope.is_present(name.clone()) {
+            true => scope.set(name, value),
+            false => self.set(name, value)
+        }
     }
 
     pub fn is_present(&self, name: String) -> bool {
-        self.variables.contains_key(&name)
+        self.variables.borrow().contains_key(&name)
+    }
+
+    pub fn is_defined(&self, name: String) -> bool {
+        self.find_scope(name.clone()).is_present(name.clone()) || STD.with(|std| std.contains_key(&name))
     }
 
     pub fn find_scope(&self, name: String) -> &Scope {
@@ -97,15 +206,159 @@ impl Scope {
             if scope.is_present(name.clone()) {
                 return scope
             }
-            scope = self.previous.as_ref().unwrap()
+            scope = scope.previous.as_ref().unwrap()
         }
 
         scope
     }
 
-    pub fn throw_exception(&self, msg: String, pos: Vec<usize>) {
-        let pos = pos.iter().map(|u| (*u as i64).to_string()).collect::<Vec<String>>();
-        println!("{}: {}\n     at: {}:{}", "ERR".bold().red(), msg, self.filename, &pos.join(":"));
-        exit(-1)
+    pub fn root(&self) -> &Scope {
+        match &self.previous {
+            Some(previous) => previous.root(),
+            None => self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::{eval, evaluate, ErrorKind, Resolver, RuntimeError, STACK_SIZE};
+
+    use super::{Scope, super::types::Value};
+
+    // deep coco calls need the stack the interpreter thread runs on
+    fn on_interpreter_stack<T: Send + 'static>(run: fn() -> T) -> T {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(run)
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn runaway_recursion_is_an_error() {
+        let result = on_interpreter_stack(|| {
+            eval("
+                fun forever(n) {
+                    return forever(n + 1)
+                }
+                forever(0)
+            ").map(|_| ()).map_err(|e| e.kind())
+        });
+
+        assert_eq!(result, Err(ErrorKind::Runtime(RuntimeError::StackOverflow)));
+    }
+
+    #[test]
+    fn call_depth_is_configurable() {
+        let (result, fits) = on_interpreter_stack(|| {
+            let code = "
+                fun depth(n) {
+                    if (n == 0) return 0
+                    return 1 + depth(n - 1)
+                }
+                let fits = depth(20)
+                depth(40)
+            ";
+            let resolver = Resolver::new("<test>".to_string(), code.to_string());
+            let mut scope = Scope::new("<test>".to_string());
+            scope.max_depth = 30;
+
+            let result = evaluate(code, &resolver, &mut scope).map(|_| ()).map_err(|e| e.kind());
+
+            (result, scope.get("fits".to_string()) == Value::Integer(20))
+        });
+
+        assert_eq!(result, Err(ErrorKind::Runtime(RuntimeError::StackOverflow)));
+        assert!(fits);
+    }
+
+    #[test]
+    fn global_writes_reach_the_top_scope() {
+        let scope = eval("
+            let total = 1
+            let count = 0
+            fun run() {
+                total = 10
+                fresh = 2
+                global count = count + 1
+                global made = 3
+            }
+            run()
+            run()
+        ").unwrap();
+
+        // plain assignments stay local to the call
+        assert_eq!(scope.get("total".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("fresh".to_string()), Value::Null);
+        assert_eq!(scope.get("count".to_string()), Value::Integer(2));
+        assert_eq!(scope.get("made".to_string()), Value::Integer(3));
+    }
+
+    #[test]
+    fn builtins_resolve_without_reseeding() {
+        let mut scope = Scope::new("<test>".to_string());
+        for _ in 0..50 {
+            scope = scope.block();
+        }
+
+        assert!(matches!(scope.get("num".to_string()), Value::Function(..)));
+        assert!(scope.variables.borrow().is_empty());
+        assert!(scope.root().variables.borrow().is_empty());
+
+        let result = eval("
+            fun outer() {
+                fun inner() {
+                    return num('4')
+                }
+                return inner()
+            }
+            let four = outer()
+        ").unwrap();
+
+        assert_eq!(result.get("four".to_string()).as_number(), 4.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn log_can_be_overridden() {
+        let scope = eval("
+            let captured = []
+            fun log(value) {
+                captured.push(value)
+            }
+            fun report() {
+                log('inner')
+            }
+            log('outer')
+            report()
+        ").unwrap();
+
+        assert_eq!(scope.get("captured".to_string()), Value::Array(vec![
+            Box::new(Value::String("outer".to_string())),
+            Box::new(Value::String("inner".to_string()))
+        ]));
+    }
+
+    #[test]
+    fn ord_and_chr_round_trip() {
+        let scope = eval("
+            let a = ord('A')
+            let accented = ord('é')
+            let empty = ord('')
+            let letter = chr(97)
+            let emoji = ord(chr(128512))
+        ").unwrap();
+
+        assert_eq!(scope.get("a".to_string()), Value::Integer(65));
+        assert_eq!(scope.get("accented".to_string()), Value::Integer(233));
+        assert_eq!(scope.get("empty".to_string()), Value::Null);
+        assert_eq!(scope.get("letter".to_string()), Value::String("a".to_string()));
+        assert_eq!(scope.get("emoji".to_string()), Value::Integer(128512));
+        // surrogates and fractions aren't characters
+        assert!(eval("chr(55296)").is_err());
+        assert!(eval("chr(97.5)").is_err());
+    }
+}
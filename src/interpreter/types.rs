@@ -1,31 +1,132 @@
-use std::{collections::{BTreeMap, HashMap}, cmp::Ordering};
+use std::{collections::{BTreeMap, HashMap}, cmp::Ordering, rc::Rc};
 
 use colored::Colorize;
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::parser::Node;
+use crate::{parser::Node, Error, RuntimeError};
 
-use super::{scope::{Scope}};
+use super::{scope::{Scope}, walk_tree};
 
 
 
 lazy_static! {
     static ref VAR_REGEX: Regex = Regex::new(r"\$([a-zA-Z][0-9a-zA-Z_]*)").unwrap();
+    static ref NUMBER_REGEX: Regex = Regex::new(r"^[+-]?(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?$").unwrap();
 }
 
+// accepts only decimal numbers: an optional sign, digits with an optional
+// point (`1`, `1.`, `1.5`, `.5`) and an optional exponent (`1e3`, `2.5E-2`).
+// unlike f64::from_str, spellings like `inf` and `nan` are rejected
+pub fn parse_number(text: &str) -> Option<f64> {
+    match NUMBER_REGEX.is_match(text) {
+        true => text.parse::<f64>().ok(),
+        false => None
+    }
+}
 
+// whole numbers print without a decimal point, fractions are cut to 15
+// significant digits so float noise like `0.1 + 0.2` shows up as `0.3`.
+// from 1e21 on numbers print in exponent form (`1e21`)
+pub fn format_number(num: f64) -> String {
+    if num == 0.0 {
+        return "0".to_string()
+    }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+    if !num.is_finite() {
+        return num.to_string()
+    }
+
+    if num.abs() >= 1e21 {
+        return format!("{:e}", format!("{:.14e}", num).parse::<f64>().unwrap_or(num))
+    }
+
+    if num.fract() == 0.0 {
+        return num.to_string()
+    }
+
+    format!("{:.14e}", num).parse::<f64>().unwrap_or(num).to_string()
+}
+
+
+
+// object keys are shown the way they'd be written in a literal:
+// words and numbers as they are, anything else quoted
+fn display_key(key: &str) -> String {
+    let is_word = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    match is_word || parse_number(key).is_some() {
+        true => key.to_string(),
+        false => format!("'{}'", key)
+    }
+}
+
+// the fields of an instance, without the methods it got from its class
+pub fn own_fields(fields: &BTreeMap<String, Box<Value>>) -> BTreeMap<String, Box<Value>> {
+    fields
+    .iter()
+    .filter(|field| !matches!(**field.1, Value::Function(..)))
+    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+    .collect()
+}
+
+#[derive(Clone)]
 pub enum FuncImpl {
-    FromNode(Node),
-    Builtin(fn(HashMap<String, Value>) -> Value)
+    // the body and the scope the function was defined in
+    FromNode(Node, Rc<Scope>),
+    Builtin(fn(HashMap<String, Value>) -> Result<Value, Error>),
+    // builtins that call back into the interpreter
+    Callback(fn(HashMap<String, Value>, &mut Scope) -> Result<Value, Error>)
+}
+
+impl FuncImpl {
+    fn rank(&self) -> u8 {
+        match self {
+            FuncImpl::FromNode(_, _) => 0,
+            FuncImpl::Builtin(_) => 1,
+            FuncImpl::Callback(_) => 2
+        }
+    }
+}
+
+// the captured scope is left out, it can hold the function itself
+impl std::fmt::Debug for FuncImpl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuncImpl::FromNode(node, _) => f.debug_tuple("FromNode").field(node).finish(),
+            FuncImpl::Builtin(fun) => f.debug_tuple("Builtin").field(fun).finish(),
+            FuncImpl::Callback(fun) => f.debug_tuple("Callback").field(fun).finish()
+        }
+    }
+}
+
+impl PartialEq for FuncImpl {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FuncImpl::FromNode(a, _), FuncImpl::FromNode(b, _)) => a == b,
+            (FuncImpl::Builtin(a), FuncImpl::Builtin(b)) => std::ptr::fn_addr_eq(*a, *b),
+            (FuncImpl::Callback(a), FuncImpl::Callback(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => false
+        }
+    }
+}
+
+impl PartialOrd for FuncImpl {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (FuncImpl::FromNode(a, _), FuncImpl::FromNode(b, _)) => a.partial_cmp(b),
+            (FuncImpl::Builtin(a), FuncImpl::Builtin(b)) => (*a as usize).partial_cmp(&(*b as usize)),
+            (FuncImpl::Callback(a), FuncImpl::Callback(b)) => (*a as usize).partial_cmp(&(*b as usize)),
+            _ => self.rank().partial_cmp(&other.rank())
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum FunctionArgument {
     Required(String),
-    NotRequired(String, Value),
+    NotRequired(String, Node),
     Spread(String)
 }
 
@@ -49,18 +150,22 @@ impl FunctionArguments {
         self.args.clone()
     }
 
-    pub fn reduce(&mut self, args_eval: &mut Vec<Value>) -> HashMap<String, Value> {
+    pub fn reduce(&mut self, args_eval: &mut Vec<Value>, scope: &mut Scope) -> Result<HashMap<String, Value>, Error> {
         args_eval.reverse();
-        self.args.clone().into_iter().fold(HashMap::default(), | mut acc, value | {
+        self.args.clone().into_iter().try_fold(HashMap::default(), | mut acc, value | {
             match value {
                 FunctionArgument::Required(name) => {
-                    acc.insert(name, args_eval.pop().unwrap());
-                    acc
+                    acc.insert(name, args_eval.pop().unwrap_or(Value::Null));
+                    Ok(acc)
                 },
-                FunctionArgument::NotRequired(name, value) => {
-                    let current_val = args_eval.pop();
-                    acc.insert(name, current_val.unwrap_or(value));
-                    acc
+                FunctionArgument::NotRequired(name, default) => {
+                    // defaults are evaluated on each call that leaves them out
+                    let current_val = match args_eval.pop() {
+                        Some(val) => val,
+                        None => walk_tree(default, scope)?
+                    };
+                    acc.insert(name, current_val);
+                    Ok(acc)
                 },
                 FunctionArgument::Spread(name) => {
                     let mut spreaded = args_eval.clone();
@@ -68,7 +173,7 @@ impl FunctionArguments {
                     acc.insert(name, Value::Array(
                         spreaded.iter().map(|v| Box::new(v.to_owned())).collect::<Vec<Box<Value>>>()
                     ));
-                    acc
+                    Ok(acc)
                 }
             }
         })
@@ -78,12 +183,17 @@ impl FunctionArguments {
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum Value {
     String(String),
+    // whole number literals and arithmetic on them stay exact, anything
+    // that doesn't fit an i64 (or isn't whole) becomes a Number
+    Integer(i64),
     Number(f64),
     Boolean(bool),
     Array(Vec<Box<Value>>),
     Object(BTreeMap<String, Box<Value>>),
     Function(String, FunctionArguments, FuncImpl),
-    Class(String, Option<Box<Value>>, BTreeMap<String, Box<Value>>),
+    Class(String, Option<Box<Value>>, BTreeMap<String, Box<Value>>, Option<Box<Value>>),
+    // made by calling a class: the class name and the fields, methods included
+    Instance(String, BTreeMap<String, Box<Value>>),
     Null
 }
 
@@ -100,149 +210,245 @@ impl Value {
         Value::String(new_string)
     }
 
+    pub fn type_name(&self) -> &str {
+        match self {
+            Value::String(_val) => "string",
+            Value::Integer(_) | Value::Number(_) => "number",
+            Value::Boolean(_val) => "boolean",
+            Value::Array(_values) => "array",
+            Value::Function(_n, _a, _i) => "function",
+            Value::Object(_map) => "object",
+            Value::Null => "null",
+            Value::Class(_n, _p, _c, _s) => "class",
+            Value::Instance(_n, _f) => "object"
+        }
+    }
+
     pub fn as_bool(&self) -> bool {
         match self {
             Value::String(val) => !val.is_empty(),
-            Value::Number(val) => *val as i64 == 0,
+            Value::Integer(val) => *val != 0,
+            Value::Number(val) => *val != 0.0 && !val.is_nan(),
             Value::Boolean(val) => *val,
             Value::Array(values) => !values.is_empty(),
             Value::Function(_n, _a, _i) => true,
             Value::Object(map) => !map.is_empty(),
             Value::Null => false,
-            Value::Class(_n, _p, _c) => true
+            Value::Class(_n, _p, _c, _s) => true,
+            Value::Instance(_n, _f) => true
         }
     }
 
     pub fn as_number(&self) -> f64 {
         match self {
-            Value::String(val) => val.parse::<f64>().unwrap_or(f64::NAN),
+            Value::String(val) => parse_number(val).unwrap_or(f64::NAN),
+            Value::Integer(val) => *val as f64,
             Value::Number(val) => *val,
             Value::Boolean(val) => *val as i64 as f64,
             Value::Array(_values) => f64::NAN,
             Value::Function(_n, _a, _i) => f64::NAN,
             Value::Object(_map) => f64::NAN,
             Value::Null => 0.0,
-            Value::Class(_n, _p, _c) => f64::NAN
+            Value::Class(_n, _p, _c, _s) => f64::NAN,
+            Value::Instance(_n, _f) => f64::NAN
         }
     }
 
     pub fn as_string(&self) -> String {
         match self {
             Value::String(val) => val.to_owned(),
-            Value::Number(val) => val.to_string(),
+            Value::Integer(val) => val.to_string(),
+            Value::Number(val) => format_number(*val),
             Value::Boolean(val) => val.to_string(),
             Value::Array(values) => values.iter().map(|x| x.as_string()).collect::<Vec<_>>().join(","),
             Value::Function(name, _s, _n) => format!("fun {} {{ ... }}", name),
             Value::Object(map) => map.iter()
             .map(|x| (x.0, *x.1.to_owned()))
-            .map(|x| format!("{}: {}", x.0, x.1.as_string()))
+            .map(|x| format!("{}: {}", display_key(x.0), x.1.as_string()))
             .collect::<Vec<_>>().join(", "),
             Value::Null => "null".to_owned(),
-            Value::Class(name, _p, _c) => format!("class {} {{ ... }}", name)
+            Value::Class(name, _p, _c, _s) => format!("class {} {{ ... }}", name),
+            // methods are left out, only the fields are shown
+            Value::Instance(name, fields) => format!(
+                "{} {{ {} }}",
+                name,
+                own_fields(fields).iter()
+                .map(|field| format!("{}: {}", display_key(field.0), field.1.as_string()))
+                .collect::<Vec<_>>().join(", ")
+            )
+        }
+    }
+
+    // same layout as Display, but without ANSI colors
+    pub fn as_raw_string(&self) -> String {
+        match self {
+            Value::String(_val) => format!("'{}'", self.as_string()),
+            Value::Array(values) => format!("[ {} ]", values.iter().map(|x| x.as_raw_string()).collect::<Vec<_>>().join(", ")),
+            Value::Object(_map) => format!("{{ {} }}", self.as_string()),
+            _ => self.as_string()
         }
     }
 
+    // == semantics: arrays and objects are compared element by element,
+    // everything else the way compare() orders it
+    pub fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y)),
+            (Value::Object(a), Value::Object(b)) => a.len() == b.len() && a.iter().all(|(key, x)| b.get(key).is_some_and(|y| x.equals(y))),
+            (Value::Instance(a_class, a), Value::Instance(b_class, b)) => a_class == b_class && Value::Object(a.to_owned()).equals(&Value::Object(b.to_owned())),
+            (Value::Array(_) | Value::Object(_) | Value::Instance(..), _) | (_, Value::Array(_) | Value::Object(_) | Value::Instance(..)) => false,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            // NaN never equals anything
+            (Value::Integer(_) | Value::Number(_), _) => self.as_number() == other.as_number(),
+            _ => self.compare(other.to_owned()) == Ordering::Equal
+        }
+    }
+
+    // structural equality: containers are compared by their items, objects by
+    // looking each key up (so the order keys are kept in never matters) and
+    // functions are the same when they have the same name and implementation.
+    // other values have to be of the same type
+    pub fn deep_equals(&self, other: &Value) -> bool {
+        let same_fields = |a: &BTreeMap<String, Box<Value>>, b: &BTreeMap<String, Box<Value>>| {
+            a.len() == b.len() && a.iter().all(|(key, x)| b.get(key).is_some_and(|y| x.deep_equals(y)))
+        };
+
+        match (self, other) {
+            (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.deep_equals(y)),
+            (Value::Object(a), Value::Object(b)) => same_fields(a, b),
+            (Value::Instance(a_class, a), Value::Instance(b_class, b)) => a_class == b_class && same_fields(a, b),
+            (Value::Function(a_name, _, a), Value::Function(b_name, _, b)) => a_name == b_name && a == b,
+            // unlike ==, 1 and '1' are different
+            _ => self.type_name() == other.type_name() && self.equals(other)
+        }
+    }
+
+    // values that can't be ordered (like arrays holding NaN) count as equal
     pub fn compare(&self, value: Value) -> Ordering {
         match self {
             Value::String(val) => val.cmp(&value.as_string()),
+            Value::Integer(val) => match value {
+                Value::Integer(other) => val.cmp(&other),
+                _ => (*val as f64).total_cmp(&value.as_number())
+            },
             Value::Number(val) => val.total_cmp(&value.as_number()),
             Value::Boolean(val) => val.cmp(&value.as_bool()),
-            Value::Array(_values) => self.partial_cmp(&value).unwrap(),
-            Value::Function(_n, _a, _i) => self.partial_cmp(&value).unwrap(),
-            Value::Object(_map) => self.partial_cmp(&value).unwrap(),
-            Value::Null => self.partial_cmp(&value).unwrap(),
-            Value::Class(_n, _p, _c) => self.partial_cmp(&value).unwrap()
+            Value::Array(_values) => self.partial_cmp(&value).unwrap_or(Ordering::Equal),
+            Value::Function(_n, _a, _i) => self.partial_cmp(&value).unwrap_or(Ordering::Equal),
+            Value::Object(_map) => self.partial_cmp(&value).unwrap_or(Ordering::Equal),
+            Value::Null => self.partial_cmp(&value).unwrap_or(Ordering::Equal),
+            Value::Class(_n, _p, _c, _s) => self.partial_cmp(&value).unwrap_or(Ordering::Equal),
+            Value::Instance(_n, _f) => self.partial_cmp(&value).unwrap_or(Ordering::Equal)
+        }
+    }
+
+    // converts an index into a position inside a container of `len` items,
+    // negative indices count from the end
+    pub fn as_index(&self, len: usize) -> Result<usize, Error> {
+        if let Value::Integer(index) = self {
+            let position = match *index < 0 {
+                true => (len as i64).checked_add(*index),
+                false => Some(*index)
+            };
+
+            return match position {
+                Some(position) if position >= 0 => Ok(position as usize),
+                _ => Err(Error::runtime(RuntimeError::IndexOutOfRange, format!("Index out of range: {}", index)))
+            }
+        }
+
+        let mut index = self.as_number();
+
+        if !index.is_finite() {
+            return Err(Error::runtime(RuntimeError::InvalidIndex, format!("Invalid index: {}", self.as_string())))
+        }
+
+        if index < 0.0 {
+            index += len as f64;
+        }
+
+        if index < 0.0 || index >= usize::MAX as f64 {
+            return Err(Error::runtime(RuntimeError::IndexOutOfRange, format!("Index out of range: {}", self.as_string())))
+        }
+
+        Ok(index as usize)
+    }
+
+    // object keys are strings. numbers and booleans are converted the way they
+    // print, so `1` and `"1"` (or `true` and `"true"`) name the same key
+    pub fn as_key(&self) -> Result<String, Error> {
+        match self {
+            Value::String(_) | Value::Integer(_) | Value::Number(_) | Value::Boolean(_) => Ok(self.as_string()),
+            _ => Err(Error::runtime(RuntimeError::InvalidKey, format!("{} cannot be used as an object key", self.type_name())))
         }
     }
 
-    pub fn get_field(&mut self, field: Value, scope: &mut Scope) -> Value {
+    pub fn get_field(&mut self, field: Value) -> Result<Value, Error> {
         match self {
             Value::String(string) => {
                 match field {
                     Value::String(val) => {
                         match val.as_str() {
-                            "length" => Value::Number(string.len() as f64),
-                            _ => Value::Null
+                            "length" => Ok(Value::Integer(string.len() as i64)),
+                            _ => Ok(Value::Null)
                         }
                     },
-                    Value::Number(val) => {
-                        if val.is_sign_negative() {
-                            string.reverse();
-                        }
-
-                        let index = val.abs() as usize;
+                    Value::Integer(_) | Value::Number(_) => {
+                        let index = field.as_index(string.len())?;
 
-                        Value::String(string.get(index..index+1).unwrap().to_string())
+                        Ok(string.get(index..index+1).map(|s| Value::String(s.to_string())).unwrap_or(Value::Null))
                     },
-                    _ => panic!("Expected number or string")
+                    _ => Err(Error::runtime(RuntimeError::TypeMismatch, "Expected number or string".to_string()))
                 }
             },
             Value::Array(array) => {
                 match field {
                     Value::String(val) => {
                         match val.as_str() {
-                            "length" => Value::Number(array.len() as f64),
-                            _ => Value::Null
+                            "length" => Ok(Value::Integer(array.len() as i64)),
+                            _ => Ok(Value::Null)
                         }
                     },
-                    Value::Number(mut val) => {
-                        if val.is_sign_negative() {
-                            val += array.len() as f64;    
-                        }
+                    Value::Integer(_) | Value::Number(_) => {
+                        let index = field.as_index(array.len())?;
 
-                        *array.get(val as usize).unwrap_or(&Box::new(Value::Null)).to_owned()
+                        Ok(*array.get(index).unwrap_or(&Box::new(Value::Null)).to_owned())
                     },
-                    _ => {
-                        scope.throw_exception("Expected number or string".to_string(), vec![0,0]);
-                        Value::Null
-                    }
+                    _ => Err(Error::runtime(RuntimeError::TypeMismatch, "Expected number or string".to_string()))
                 }
             },
-            Value::Object(map) => {
-                match field {
-                    Value::String(val) => {
-                        *map.to_owned().get(&val).unwrap_or(&Box::new(Value::Null)).to_owned()
-                    },
-                    // FIXME
-                    _ => {
-                        scope.throw_exception("Unknown field".to_string(), vec![0,0]);
-                        Value::Null
-                    }
-                }
+            Value::Object(map) | Value::Instance(_, map) => {
+                Ok(map.get(&field.as_key()?).map(|value| *value.to_owned()).unwrap_or(Value::Null))
             },
-            _ => Value::Null,
+            _ => Ok(Value::Null),
         }
     }
 
-    pub fn set_field(&mut self, field: Value, value: Value) -> Value {
+    pub fn set_field(&mut self, field: Value, value: Value) -> Result<Value, Error> {
         match self {
             Value::Array(array) => {
                 match field {
-                    Value::Number(val) => {
-                        if val.is_sign_negative() {
-                            let len = array.len() as f64;
-                            array[(len + val) as usize] = Box::new(value);
-                        } else {
-                            array[val as usize] = Box::new(value);
+                    Value::Integer(_) | Value::Number(_) => {
+                        let index = field.as_index(array.len())?;
+
+                        match array.get_mut(index) {
+                            Some(item) => **item = value,
+                            None => return Err(Error::runtime(RuntimeError::IndexOutOfRange, format!("Index out of range: {}", field.as_string())))
                         }
 
-                        self.to_owned()
+                        Ok(self.to_owned())
                     },
-                    _ => panic!("Expected number")
+                    _ => Err(Error::runtime(RuntimeError::TypeMismatch, "Expected number".to_string()))
                 }
             },
-            Value::Object(map) => {
-                if let Value::String(val) = field {
-                    map.insert(val, Box::new(value));
+            Value::Object(map) | Value::Instance(_, map) => {
+                map.insert(field.as_key()?, Box::new(value));
 
-                    self.to_owned()
-                } else {
-                    panic!("Unknown field")
-                }
+                Ok(self.to_owned())
             },
 
             // FIXME
-            _ => panic!("Cannot set field to this value")
+            _ => Err(Error::runtime(RuntimeError::TypeMismatch, "Cannot set field to this value".to_string()))
         }
     }
 }
@@ -258,44 +464,33 @@ impl FieldAccessor {
         Self { value, fields }
     }
 
-    pub fn get(&mut self, scope: &mut Scope) -> Value {
-        let mut container = self.get_container(scope);
+    pub fn get(&mut self) -> Result<Value, Error> {
+        let mut container = self.get_container()?;
         let last = self.last();
 
         match container.clone() {
-            Value::String(_val) => container.get_field(last, scope),
-            Value::Array(_vals) => container.get_field(last, scope),
-            Value::Object(_vals) => container.get_field(last, scope),
-            _ => panic!("Array, string or object expected")
+            Value::String(_val) => container.get_field(last),
+            Value::Array(_vals) => container.get_field(last),
+            Value::Object(_vals) | Value::Instance(_, _vals) => container.get_field(last),
+            _ => Err(Error::runtime(RuntimeError::TypeMismatch, "Array, string or object expected".to_string()))
         }
     }
 
-    pub fn set(&mut self, value: Value, scope: &mut Scope) -> Value {
-        let mut container = self.get_container(scope);
-        let last = self.last();
-
-        match container.clone() {
-            Value::Array(_vals) => container.set_field(last, value),
-            Value::Object(_vals) => container.set_field(last, value),
-            _ => panic!("Array or object expected")
-        }
+    // returns the whole value with the field at the end of the path replaced
+    pub fn set(&mut self, value: Value) -> Result<Value, Error> {
+        set_in(self.value.clone(), &self.fields, value)
     }
 
-    pub fn get_container(&mut self, scope: &mut Scope) -> Value {
+    pub fn get_container(&mut self) -> Result<Value, Error> {
         let mut container = self.value.clone();
-        for i in 0..self.fields.len() - 1 {
-            match self.value.clone() {
-                Value::Array(_val) => {
-                    container = self.value.get_field(self.fields.get(i).unwrap().to_owned(), scope)
-                },
-                Value::Object(_val) => {
-                    container = self.value.get_field(self.fields.get(i).unwrap().to_owned(), scope)
-                },
-                _ => panic!("Array or object expected"),
+        for field in &self.fields[..self.fields.len() - 1] {
+            container = match container {
+                Value::Array(_) | Value::Object(_) | Value::Instance(..) => container.get_field(field.to_owned())?,
+                _ => return Err(Error::runtime(RuntimeError::TypeMismatch, "Array or object expected".to_string())),
             }
         }
 
-        container
+        Ok(container)
     }
 
     pub fn last(&self) -> Value {
@@ -307,18 +502,159 @@ impl FieldAccessor {
     }
 }
 
+// every container along the path is copied with its changed child put back
+fn set_in(mut container: Value, fields: &[Value], value: Value) -> Result<Value, Error> {
+    let (field, rest) = match fields.split_first() {
+        Some(split) => split,
+        None => return Ok(value)
+    };
+
+    if !matches!(container, Value::Array(_) | Value::Object(_) | Value::Instance(..)) {
+        return Err(Error::runtime(RuntimeError::TypeMismatch, "Array or object expected".to_string()))
+    }
+
+    let child = match rest.is_empty() {
+        true => value,
+        false => set_in(container.get_field(field.to_owned())?, rest, value)?
+    };
+
+    container.set_field(field.to_owned(), child)
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         
         match self {
             Value::String(_val) => write!(f, "{}", ("'".to_owned() + &self.as_string() + "'").green()),
-            Value::Number(_val) => write!(f, "{}", &self.as_string().yellow()),
+            Value::Integer(_) | Value::Number(_) => write!(f, "{}", &self.as_string().yellow()),
             Value::Boolean(_val) => write!(f, "{}", &self.as_string().blue()),
             Value::Array(values) => write!(f, "[ {} ]", values.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")),
             Value::Function(name, _a, _i) => write!(f, "fun {} {{ ... }}", name),
             Value::Object(_map) => write!(f, "{{ {} }}", &self.as_string()),
             Value::Null => write!(f, "{}", "null".bold()),
-            Value::Class(name, _p, _c) => write!(f, "class {} {{ ... }}", name),
+            Value::Class(name, _p, _c, _s) => write!(f, "class {} {{ ... }}", name),
+            Value::Instance(_n, _f) => write!(f, "{}", &self.as_string()),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::{eval, ErrorKind, RuntimeError};
+
+    use super::Value;
+
+    const POINT: &str = "
+        class Point {
+            constructor(x, y) {
+                this.x = x
+                this.y = y
+            }
+
+            sum() {
+                return this.x + this.y
+            }
         }
+        let p = new Point(1, 2)
+    ";
+
+    fn run(code: &str) -> Value {
+        eval(&format!("{}\nlet result = {}", POINT, code)).unwrap().get("result".to_string())
+    }
+
+    #[test]
+    fn classes_and_instances_print_differently() {
+        assert_eq!(run("Point").as_string(), "class Point { ... }");
+        assert_eq!(run("p").as_string(), "Point { x: 1, y: 2 }");
+        assert_eq!(run("typeof Point"), Value::String("class".to_string()));
+        assert_eq!(run("typeof p"), Value::String("object".to_string()));
+    }
+
+    #[test]
+    fn instances_behave_like_objects_of_their_fields() {
+        let keys = Value::Array(vec![Box::new(Value::String("x".to_string())), Box::new(Value::String("y".to_string()))]);
+
+        assert_eq!(run("p.keys()"), keys);
+        assert_eq!(run("p.has('x') && !p.has('sum')"), Value::Boolean(true));
+        assert_eq!(run("for (key in p) { key }"), keys);
+        assert_eq!(run("p.sum()"), Value::Integer(3));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn raw_strings_have_no_ansi_codes() {
+        let text = run("rawString([1, 'a', { b: true }])").as_string();
+
+        assert!(!text.contains('\u{1b}'), "{:?}", text);
+        assert_eq!(text, "[ 1, 'a', { b: true } ]");
+    }
+
+    #[test]
+    fn methods_see_this() {
+        let scope = eval("
+            class Rect {
+                constructor(height, width) {
+                    this.height = height
+                    this.width = width
+                }
+
+                area() {
+                    return this.height * this.width
+                }
+            }
+            let rect = new Rect(2, 3)
+            let area = rect.area()
+        ").unwrap();
+
+        assert_eq!(scope.get("area".to_string()), Value::Integer(6));
+    }
+
+    #[test]
+    fn nan_and_huge_indexes_are_errors() {
+        let nan = eval("let list = [1, 2, 3]\nlist[0 / 0]").err().unwrap();
+        let huge = eval("let list = [1, 2, 3]\nlist[10.0 ** 300]").err().unwrap();
+
+        assert_eq!(nan.kind(), ErrorKind::Runtime(RuntimeError::InvalidIndex));
+        assert_eq!(huge.kind(), ErrorKind::Runtime(RuntimeError::IndexOutOfRange));
+        assert!(Value::Number(f64::NAN).as_index(3).is_err());
+        assert!(Value::Number(1e300).as_index(3).is_err());
+    }
+
+    #[test]
+    fn keys_are_coerced_to_strings() {
+        let scope = eval("
+            let lookup = { 1: 'one', true: 'yes' }
+            let byNumber = lookup[1]
+            let byString = lookup['1']
+            let byBoolean = lookup[true]
+            let keys = lookup.keys()
+            let collided = { 1: 'a', '1': 'b' }
+            let collidedKeys = collided.keys()
+            let collidedValue = collided[1]
+            let assigned = {}
+            assigned[2] = 'two'
+            let assignedKeys = assigned.keys()
+        ").unwrap();
+
+        let strings = |values: &[&str]| Value::Array(values.iter().map(|s| Box::new(Value::String(s.to_string()))).collect());
+
+        assert_eq!(scope.get("byNumber".to_string()), Value::String("one".to_string()));
+        assert_eq!(scope.get("byString".to_string()), Value::String("one".to_string()));
+        assert_eq!(scope.get("byBoolean".to_string()), Value::String("yes".to_string()));
+        assert_eq!(scope.get("keys".to_string()), strings(&["1", "true"]));
+        // 1 and '1' are the same key, the later one wins
+        assert_eq!(scope.get("collidedKeys".to_string()), strings(&["1"]));
+        assert_eq!(scope.get("collidedValue".to_string()), Value::String("b".to_string()));
+        assert_eq!(scope.get("assignedKeys".to_string()), strings(&["2"]));
+    }
+
+    #[test]
+    fn only_decimal_numbers_parse() {
+        assert_eq!(super::parse_number("1e3"), Some(1000.0));
+        assert_eq!(super::parse_number(".5"), Some(0.5));
+        assert_eq!(super::parse_number("inf"), None);
+        assert_eq!(super::parse_number("nan"), None);
+        assert_eq!(super::parse_number("1e"), None);
+
+        assert!(run("num('inf')").as_number().is_nan());
+        assert!(run("num('1e')").as_number().is_nan());
+    }
+}
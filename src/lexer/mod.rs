@@ -1,6 +1,6 @@
 use phf::{ phf_map };
 
-use crate::{Error, Resolver};
+use crate::{Error, LexError, Resolver};
 
 const QUOTES: &str = "\'\"";
 const LETTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
@@ -9,6 +9,7 @@ const DIGITS: &str = "0123456789";
 
 const KEYWORDS: phf::Map<&str, TokenType> = phf_map! {
     "let" => TokenType::LET,
+    "global" => TokenType::GLOBAL,
     "fun" =>  TokenType::FUN,
     "return" =>  TokenType::RETURN,
     "if" =>  TokenType::IF,
@@ -29,10 +30,14 @@ const KEYWORDS: phf::Map<&str, TokenType> = phf_map! {
     "class" =>  TokenType::CLASS,
     "new" =>  TokenType::NEW,
     "this" =>  TokenType::THIS,
+    "super" =>  TokenType::SUPER,
     "import" => TokenType::IMPORT,
     "from" => TokenType::FROM,
     "NaN" => TokenType::NAN,
     "as" => TokenType::AS,
+    "try" => TokenType::TRY,
+    "catch" => TokenType::CATCH,
+    "throw" => TokenType::THROW,
 };
 
 const OPERATORS: phf::Map<&str, TokenType> = phf_map! {
@@ -53,8 +58,8 @@ const OPERATORS: phf::Map<&str, TokenType> = phf_map! {
     "!=" => TokenType::EXCLEQ,
     ">" => TokenType::GT,
     "<" => TokenType::LT,
-    "<=" => TokenType::GTEQ,
-    ">=" => TokenType::LTEQ,
+    "<=" => TokenType::LTEQ,
+    ">=" => TokenType::GTEQ,
     "&&" => TokenType::AMPAMP,
     "||" => TokenType::BARBAR,
     "->" => TokenType::ARROW,
@@ -70,7 +75,9 @@ const OPERATORS: phf::Map<&str, TokenType> = phf_map! {
     "*=" => TokenType::MULTIPLYEQ,
     "/=" => TokenType::DIVIDEEQ,
     "**=" => TokenType::EXPONENTEQ,
-    "%=" => TokenType::REMAINDEREQ
+    "%=" => TokenType::REMAINDEREQ,
+    "++" => TokenType::PLUSPLUS,
+    "--" => TokenType::MINUSMINUS
 };
 
 fn is_variable(var: char) -> bool{
@@ -81,6 +88,7 @@ fn is_variable(var: char) -> bool{
 #[allow(dead_code)]
 pub enum TokenType {
     LET, // let
+    GLOBAL, // global
     FUN, // fun
     RETURN, // return
     FOR, // for
@@ -98,9 +106,13 @@ pub enum TokenType {
     CLASS, // class
     NEW, // new
     THIS, // this
+    SUPER, // super
     IMPORT, // import
     FROM, // from
     AS, // as
+    TRY, // try
+    CATCH, // catch
+    THROW, // throw
     
     NULL, // null
     NUMBER, // 0
@@ -108,6 +120,7 @@ pub enum TokenType {
     WORD, // bones
     BOOLEAN, // true, false
     NAN, // NaN
+    UNIT, // the ms in 100ms
 
     EQUALS, // =
     PLUS, // +
@@ -123,6 +136,8 @@ pub enum TokenType {
     MULTIPLYEQ, // *=
     EXPONENTEQ, // **=
     REMAINDEREQ, // %=
+    PLUSPLUS, // ++
+    MINUSMINUS, // --
 
     LPAR, // (
     RPAR, // )
@@ -139,8 +154,8 @@ pub enum TokenType {
     EXCLEQ, // !=
     GT, // >
     LT, // <
-    GTEQ, // <=
-    LTEQ, // >=
+    GTEQ, // >=
+    LTEQ, // <=
     AMPAMP, // &&
     BARBAR, // ||
     ARROW, // ->
@@ -160,6 +175,8 @@ pub struct Token {
 #[derive(Debug, Clone)]
 pub struct Lexer {
     pub code: String,
+    // the code split up front, positions index into it
+    chars: Vec<char>,
     pub tokens: Vec<Token>,
     pub pos: usize,
     pub resolver: Resolver
@@ -169,6 +186,7 @@ impl Lexer {
     pub fn new(input: &str, resolver: &Resolver) -> Self {
         Self {
             code: input.to_owned(),
+            chars: input.chars().collect(),
             tokens: Vec::new(),
             pos: 0,
             resolver: resolver.to_owned()
@@ -176,7 +194,7 @@ impl Lexer {
     }
 
     pub fn analyse(&mut self) -> Result<(), Error> {
-        while self.pos < self.code.len() {
+        while self.pos < self.chars.len() {
             let current = self.peek(None);
             let mut result = None;
 
@@ -192,10 +210,8 @@ impl Lexer {
                 self.next_char();
             }
 
-            if result.is_some() && result.as_ref().unwrap().is_err() {
-                if let Some(s) = result {
-                    return Err(s.err().unwrap_or_else(|| Error { msg: "Unexpected error".to_string(), pos: vec![] }))
-                }
+            if let Some(Err(e)) = result {
+                return Err(e)
             }
         }
 
@@ -203,6 +219,7 @@ impl Lexer {
     }
 
     pub fn parse_operator(&mut self) -> Result<(), Error> {
+        let start = self.pos;
         let mut buffer: String = "".to_owned();
         let mut current = self.peek(None);
         loop {
@@ -219,12 +236,13 @@ impl Lexer {
             current = self.next_char();
         }
 
-        self.add_token(OPERATORS.get(buffer.as_str()).unwrap().to_owned(), buffer.as_str());
+        self.add_token(OPERATORS.get(buffer.as_str()).unwrap().to_owned(), buffer.as_str(), start);
         
         Ok(())
     }
 
     pub fn parse_number(&mut self) -> Result<(), Error> {
+        let start = self.pos;
         let mut buffer: String = "".to_owned();
         let mut current = self.peek(None);
 
@@ -243,22 +261,32 @@ impl Lexer {
             current = self.next_char();
         }
 
-        self.add_token(TokenType::NUMBER, buffer.as_str());
+        self.add_token(TokenType::NUMBER, buffer.as_str(), start);
+
+        // a word right after the digits is a unit suffix: 100ms, 2kb
+        if LETTERS.contains(current) {
+            let unit_start = self.pos;
+            let mut unit = String::new();
+            while is_variable(current) {
+                unit.push(current);
+                current = self.next_char();
+            }
+
+            self.add_token(TokenType::UNIT, unit.as_str(), unit_start);
+        }
 
         Ok(())
     }
 
     pub fn parse_string(&mut self) -> Result<(), Error> {
+        let start = self.pos;
         let mut buffer: String = "".to_owned();
         let quote = self.peek(None);
         let mut current = self.next_char();
 
         loop {
             if current == '\0' {
-                return Err(Error { 
-                    msg: "String did not close".to_string(), 
-                    pos: self.resolver.resolve_where(self.pos) 
-                });
+                return Err(Error::lex(LexError::UnterminatedString, "String did not close".to_string(), self.resolver.resolve_where(start)));
             }
             if current == quote {
                 break;
@@ -268,12 +296,13 @@ impl Lexer {
         }
 
         self.next_char();
-        self.add_token(TokenType::STRING, buffer.as_str());
+        self.add_token(TokenType::STRING, buffer.as_str(), start);
 
         Ok(())
     }
 
     pub fn parse_word(&mut self) -> Result<(), Error> {
+        let start = self.pos;
         let mut buffer: String = "".to_owned();
         let mut current = self.peek(None);
         loop {
@@ -285,11 +314,11 @@ impl Lexer {
         }
 
         if KEYWORDS.contains_key(buffer.as_str()) {
-            self.add_token(KEYWORDS.get(buffer.as_str()).unwrap().to_owned(), buffer.as_str());
+            self.add_token(KEYWORDS.get(buffer.as_str()).unwrap().to_owned(), buffer.as_str(), start);
             return Ok(())
         }
 
-        self.add_token(TokenType::WORD, buffer.as_str());
+        self.add_token(TokenType::WORD, buffer.as_str(), start);
         
         Ok(())
     }
@@ -302,10 +331,7 @@ impl Lexer {
                     break
                 }
                 if current == '\0' {
-                    return Err(Error { 
-                        msg: "Multiline comment did not close".to_string(), 
-                        pos: self.resolver.resolve_where(self.pos) 
-                    });
+                    return Err(Error::lex(LexError::UnterminatedComment, "Multiline comment did not close".to_string(), self.resolver.resolve_where(self.pos)));
                 }
                 self.next_char();
             }
@@ -325,7 +351,7 @@ impl Lexer {
     pub fn peek(&self, pos: Option<usize>) -> char {
         let current = self.pos + pos.unwrap_or(0);
 
-        self.code.chars().nth(current).unwrap_or('\0')
+        self.chars.get(current).copied().unwrap_or('\0')
     }
 
     pub fn next_char(&mut self) -> char {
@@ -333,9 +359,30 @@ impl Lexer {
         self.peek(None)
     }
 
-    pub fn add_token(&mut self, token_type: TokenType, text: &str) {
-        let token = Token { token_type, text: text.to_owned(), pos: self.pos };
+    // tokens keep the position they start at
+    pub fn add_token(&mut self, token_type: TokenType, text: &str, pos: usize) {
+        let token = Token { token_type, text: text.to_owned(), pos };
 
         self.tokens.push(token)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_types(code: &str) -> Vec<TokenType> {
+        let resolver = Resolver::new("<test>".to_string(), code.to_string());
+        let mut lexer = Lexer::new(code, &resolver);
+        lexer.analyse().unwrap();
+
+        lexer.tokens.into_iter().map(|token| token.token_type).collect()
+    }
+
+    #[test]
+    fn comparison_operators() {
+        assert_eq!(token_types("a <= b"), vec![TokenType::WORD, TokenType::LTEQ, TokenType::WORD]);
+        assert_eq!(token_types("a >= b"), vec![TokenType::WORD, TokenType::GTEQ, TokenType::WORD]);
+        assert_eq!(token_types("a < b > c"), vec![TokenType::WORD, TokenType::LT, TokenType::WORD, TokenType::GT, TokenType::WORD]);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ fs, env, process::exit, io::{ self, Write }, };
+use std::{ fs, env, thread, process::exit, io::{ self, Write }, };
 
 extern crate phf;
 extern crate lazy_static;
@@ -9,29 +9,170 @@ pub mod interpreter;
 pub mod modules;
 
 use colored::Colorize;
-use lexer::{ Lexer };
-use parser::{ Parser };
-use interpreter::{ scope::{ Scope }, walk_tree };
+use lexer::{ Lexer, Token, TokenType };
+use parser::{ Node, Parser };
+use interpreter::{ scope::{ Scope }, types::{ Value }, walk_tree };
 
 pub fn error_message(msg: String) {
+    modules::io::flush_output();
     println!("{}: {msg}", "ERR".bold().red());
 }
 
 pub fn warn_message(msg: String) {
+    modules::io::flush_output();
     println!("{}: {msg}", "WARN".bold().yellow());
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LexError {
+    UnterminatedString,
+    UnterminatedComment
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseError {
+    UnexpectedToken,
+    UnexpectedEnd,
+    UnknownExpression,
+    InvalidClassBody,
+    InvalidParameters,
+    InvalidSwitch,
+    InvalidPattern,
+    OutsideLoop
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuntimeError {
+    Uncaught,
+    NotAFunction,
+    NotIterable,
+    CannotDestructure,
+    CannotExtend,
+    IndexOutOfRange,
+    InvalidIndex,
+    InvalidKey,
+    TypeMismatch,
+    InvalidArgument,
+    UnknownModule,
+    NotExported,
+    UnknownUnit,
+    Template,
+    Json,
+    StackOverflow,
+    Io
+}
+
+// what went wrong, for tools that can't rely on the wording of the message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Lex(LexError),
+    Parse(ParseError),
+    Runtime(RuntimeError)
+}
+
+impl ErrorKind {
+    // stable across releases, messages are not
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Lex(kind) => match kind {
+                LexError::UnterminatedString => "lex/unterminated-string",
+                LexError::UnterminatedComment => "lex/unterminated-comment"
+            },
+            ErrorKind::Parse(kind) => match kind {
+                ParseError::UnexpectedToken => "parse/unexpected-token",
+                ParseError::UnexpectedEnd => "parse/unexpected-end",
+                ParseError::UnknownExpression => "parse/unknown-expression",
+                ParseError::InvalidClassBody => "parse/invalid-class-body",
+                ParseError::InvalidParameters => "parse/invalid-parameters",
+                ParseError::InvalidSwitch => "parse/invalid-switch",
+                ParseError::InvalidPattern => "parse/invalid-pattern",
+                ParseError::OutsideLoop => "parse/outside-loop"
+            },
+            ErrorKind::Runtime(kind) => match kind {
+                RuntimeError::Uncaught => "runtime/uncaught",
+                RuntimeError::NotAFunction => "runtime/not-a-function",
+                RuntimeError::NotIterable => "runtime/not-iterable",
+                RuntimeError::CannotDestructure => "runtime/cannot-destructure",
+                RuntimeError::CannotExtend => "runtime/cannot-extend",
+                RuntimeError::IndexOutOfRange => "runtime/index-out-of-range",
+                RuntimeError::InvalidIndex => "runtime/invalid-index",
+                RuntimeError::InvalidKey => "runtime/invalid-key",
+                RuntimeError::TypeMismatch => "runtime/type-mismatch",
+                RuntimeError::InvalidArgument => "runtime/invalid-argument",
+                RuntimeError::UnknownModule => "runtime/unknown-module",
+                RuntimeError::NotExported => "runtime/not-exported",
+                RuntimeError::UnknownUnit => "runtime/unknown-unit",
+                RuntimeError::Template => "runtime/template",
+                RuntimeError::Json => "runtime/json",
+                RuntimeError::StackOverflow => "runtime/stack-overflow",
+                RuntimeError::Io => "runtime/io"
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Error {
     msg: String,
-    pos: Vec<usize>
+    pos: Vec<usize>,
+    kind: ErrorKind,
+    // the value of a `throw`, errors raised by the interpreter itself have none
+    thrown: Option<Box<Value>>
 }
 
 impl Error {
-    pub fn exit(&self, filename: String) {
+    pub fn new(kind: ErrorKind, msg: String, pos: Vec<usize>) -> Self {
+        Error { msg, pos, kind, thrown: None }
+    }
+
+    pub fn lex(kind: LexError, msg: String, pos: Vec<usize>) -> Self {
+        Error::new(ErrorKind::Lex(kind), msg, pos)
+    }
+
+    pub fn parse(kind: ParseError, msg: String, pos: Vec<usize>) -> Self {
+        Error::new(ErrorKind::Parse(kind), msg, pos)
+    }
+
+    // raised while running, the position is filled in by `at` on the way up
+    pub fn runtime(kind: RuntimeError, msg: String) -> Self {
+        Error::new(ErrorKind::Runtime(kind), msg, vec![0, 0])
+    }
+
+    // the error a `throw` raises, `try` catches it by the value it carries
+    pub fn thrown(value: Value, pos: Vec<usize>) -> Self {
+        Error {
+            msg: format!("Uncaught {}", value.as_string()),
+            pos,
+            kind: ErrorKind::Runtime(RuntimeError::Uncaught),
+            thrown: Some(Box::new(value))
+        }
+    }
+
+    // errors raised where the position isn't known take the one of the enclosing node
+    pub fn at(self, pos: &[usize]) -> Self {
+        match self.pos.iter().all(|p| *p == 0) {
+            true => Error { pos: pos.to_vec(), ..self },
+            false => self
+        }
+    }
+
+    // the message, where it happened and the source line it points at
+    pub fn report(&self, resolver: &Resolver) -> String {
         let pos = self.pos.iter().map(|u| (*u as i64).to_string()).collect::<Vec<String>>();
-        
-        error_message(format!("{}\n     at: {}:{}", self.msg, filename, &pos.join(":")));
+        let report = format!("{} [{}]\n     at: {}:{}", self.msg, self.kind.code(), resolver.filename, &pos.join(":"));
+
+        match resolver.show_line(&self.pos) {
+            Some(line) => format!("{}\n{}", report, line),
+            None => report
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn exit(&self, resolver: &Resolver) {
+        error_message(self.report(resolver));
         exit(-1)
     }
 }
@@ -39,72 +180,128 @@ impl Error {
 #[derive(Debug, Clone)]
 pub struct Resolver {
     filename: String,
-    code: String
+    code: String,
+    // where each line starts, so positions are found without walking the code
+    line_starts: Vec<usize>,
+    // where the code that is lexed starts, when it follows earlier code
+    offset: usize
 }
 
 impl Resolver {
     pub fn new(filename: String, code: String) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(code.match_indices('\n').map(|(i, _)| i + 1));
+
         Self {
             filename,
-            code
+            code,
+            line_starts,
+            offset: 0
         }
     } 
 
+    // `input` comes after `previous`, like a line of a repl session after the
+    // ones before it. its positions resolve to lines of the whole code
+    pub fn after(filename: String, previous: &str, input: &str) -> Self {
+        Self {
+            offset: previous.len(),
+            ..Self::new(filename, previous.to_owned() + input)
+        }
+    }
+
     pub fn resolve_where(&self, pos: usize) -> Vec<usize> {
-        let lines = self.code.split('\n');
-        let mut len: usize = 0;
-        let mut line_start: usize = 0;
-    
-        for (i, line) in lines.into_iter().enumerate() {
-            len += line.len() + 1;
-            if pos < len {
-                return vec![i + 1, pos - line_start + 1]
-            }
-            line_start = len;
+        let pos = pos + self.offset;
+        if pos > self.code.len() {
+            return vec![0, 0]
         }
-    
-        vec![0, 0]
+
+        let line = self.line_starts.partition_point(|start| *start <= pos) - 1;
+
+        vec![line + 1, pos - self.line_starts[line] + 1]
+    }
+
+    // the line at [line, column] with a caret under the column:
+    //    3 | let x = foo(1)
+    //      |            ^
+    pub fn show_line(&self, pos: &[usize]) -> Option<String> {
+        let (line, column) = match pos {
+            [line, column] if *line > 0 && *column > 0 => (*line, *column),
+            _ => return None
+        };
+        let text = self.code.split('\n').nth(line - 1)?.trim_end_matches('\r');
+
+        // tabs are kept so the caret lines up with the text above
+        let indent = text.chars().take(column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+        let number = line.to_string();
+
+        Some(format!(
+            "{} | {}\n{} | {}{}",
+            number.blue(), text, " ".repeat(number.len()), indent, "^".bold().red()
+        ))
     }
 
-    pub fn exit_error(&self, msg: String, pos: Vec<usize>) {
-        Error { msg, pos }.exit(self.filename.clone()) 
+    pub fn exit_error(&self, msg: String, pos: Vec<usize>, kind: ErrorKind) {
+        Error::new(kind, msg, pos).exit(self)
     }
 }
 
+const USAGE: &str = "usage: coco [file [args...]]
+       coco -e, --eval <code>
+       coco -h, --help
+       coco -v, --version
+
+without arguments coco starts the repl";
+
 fn run_file(filename: String) {
-    let input = fs::read_to_string(&filename).unwrap();
+    let input = match fs::read_to_string(&filename) {
+        Ok(input) => input,
+        Err(e) => {
+            error_message(format!("Cannot read {}: {}", filename, e));
+            exit(-1)
+        }
+    };
 
+    run_code(filename, input)
+}
+
+fn run_code(filename: String, input: String) {
     // creating resolver for resolving position of error
 
     let resolver = Resolver::new(filename.clone(), input.clone());
+    let mut scope = Scope::new(filename.to_string());
 
-    // getting tokens
+    if let Err(e) = evaluate(&input, &resolver, &mut scope) {
+        e.exit(&resolver)
+    }
+
+    modules::io::flush_output();
+}
 
-    let mut lexer = Lexer::new(&input, &resolver);
-    let tokens = lexer.analyse();
+// lexes, parses and runs the code in the given scope
+pub fn evaluate(input: &str, resolver: &Resolver, scope: &mut Scope) -> Result<Value, Error> {
+    // getting tokens
 
-    if let Err(e) = tokens {
-        e.exit(filename.to_string())
-    }
+    let mut lexer = Lexer::new(input, resolver);
+    lexer.analyse()?;
 
     // parsing tokens in nodes
 
-    let mut parser = Parser::new(lexer.tokens, &resolver);
-    let parsed = parser.parse();
-
-    if let Err(e) = parsed.as_ref() {
-        e.exit(filename.to_string())
-    }
+    let mut parser = Parser::new(lexer.tokens, resolver);
+    let parsed = parser.parse()?;
 
     // executing the code
-    
-    let mut scope = Scope::new(filename.to_string());
 
-    let result = walk_tree(parsed.unwrap(), &mut scope);
+    walk_tree(parsed, scope)
+}
 
-    if let Err(e) = result {
-        e.exit(filename)
-    }
+// runs a snippet in a fresh scope and hands the scope back, so tests can look at its variables
+#[cfg(test)]
+pub fn eval(code: &str) -> Result<Scope, Error> {
+    let resolver = Resolver::new("<test>".to_string(), code.to_string());
+    let mut scope = Scope::new("<test>".to_string());
+    evaluate(code, &resolver, &mut scope)?;
+
+    Ok(scope)
 }
 
 fn run_repl() {
@@ -112,48 +309,208 @@ fn run_repl() {
 
     let filename = "<repl>".to_string();
     let mut scope = Scope::new(filename.clone());
-    let resolver = Resolver::new(filename.clone(), "".to_string());
+
+    let mut buffer = String::new();
+    // every complete input so far, functions defined in one can fail in a later one
+    let mut session = String::new();
 
     loop {
-        print!(">> ");
+        modules::io::flush_output();
+        // `..` asks for the rest of an unfinished statement
+        print!("{}", if buffer.is_empty() { ">> " } else { ".. " });
         let _ = io::stdout().flush();
-        let mut buffer = String::new();
-        if let Ok(_b) = io::stdin().read_line(&mut buffer) {   
-            let mut lexer = Lexer::new(&buffer, &resolver);
-            let tokens = lexer.analyse();
-
-            if let Err(e) = tokens {
-                error_message(format!("{}\n     at: {}:0:0", e.msg, &filename));
+        match io::stdin().read_line(&mut buffer) {
+            // end of input (ctrl+d) leaves the repl
+            Ok(0) | Err(_) => {
+                println!();
                 return
-            }
+            },
+            Ok(_) => {}
+        }
 
-            // parsing tokens in nodes
+        // positions in errors count from the start of the session
+        let resolver = Resolver::after(filename.clone(), &session, &buffer);
+        let mut lexer = Lexer::new(&buffer, &resolver);
+        let tokens = lexer.analyse();
 
-            let mut parser = Parser::new(lexer.tokens, &resolver);
-            let parsed = parser.parse();
+        if let Err(e) = tokens {
+            error_message(e.report(&resolver));
+            buffer.clear();
+            continue
+        }
 
-            if let Err(e) = parsed.as_ref() {
-                error_message(format!("{}\n     at: {}:0:0", e.msg, &filename));
-                return
-            }
+        // lines are collected until every bracket they open is closed
+        if is_unclosed(&lexer.tokens) {
+            continue
+        }
+        session.push_str(&buffer);
+        buffer.clear();
 
-            let result = walk_tree(parsed.unwrap(), &mut scope);
+        // parsing tokens in nodes
 
-            if let Err(e) = result {
-                error_message(format!("{}\n     at: {}:0:0", e.msg, &filename));
-                return
+        let mut parser = Parser::new(lexer.tokens, &resolver);
+        let parsed = parser.parse();
+
+        if let Err(e) = parsed.as_ref() {
+            error_message(e.report(&resolver));
+            continue
+        }
+
+        let statements = match parsed.unwrap() {
+            Node::BlockStatement(statements) => statements,
+            node => vec![Box::new(node)]
+        };
+
+        for statement in statements {
+            let quiet = is_statement(&statement);
+            let result = walk_tree(*statement, &mut scope);
+
+            match result {
+                // the rest of the line is skipped, the session goes on
+                Err(e) => {
+                    error_message(e.report(&resolver));
+                    scope.control = None;
+                    break
+                },
+                // expressions show their value, null is left out so calls like log() stay clean
+                Ok(value) if !quiet && value != Value::Null => {
+                    modules::io::flush_output();
+                    println!("{}", value);
+                },
+                Ok(_) => {}
             }
         }
     }
 }
 
+fn is_unclosed(tokens: &[Token]) -> bool {
+    let depth = tokens.iter().fold(0, |depth, token| match token.token_type {
+        TokenType::LBRACE | TokenType::LPAR | TokenType::LBRACKET => depth + 1,
+        TokenType::RBRACE | TokenType::RPAR | TokenType::RBRACKET => depth - 1,
+        _ => depth
+    });
+
+    depth > 0
+}
+
+// definitions, assignments and control flow, the repl doesn't print their result
+fn is_statement(node: &Node) -> bool {
+    match node {
+        Node::Fun(name, _args, _block) => matches!(**name, Node::Var(_)),
+        Node::ImportPlaceholder(..) |
+        Node::ImportObjects(..) |
+        Node::Assign(..) |
+        Node::Global(..) |
+        Node::AssignOp(..) |
+        Node::Class(..) |
+        Node::IfElseStatement(..) |
+        Node::WhileStatement(..) |
+        Node::DoWhileStatement(..) |
+        Node::ForStatement(..) |
+        Node::TryCatch(..) |
+        Node::Throw(..) |
+        Node::Return(..) |
+        Node::Break |
+        Node::Continue => true,
+        _ => false
+    }
+}
+
+// every coco call nests a few walk_tree frames, the main thread's stack
+// would overflow long before the call depth limit is reached. 64 MiB holds
+// the default depth with room to spare for calls with nested blocks
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
 fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .unwrap();
+
+    if interpreter.join().is_err() {
+        exit(-1)
+    }
+}
+
+// what the command-line arguments ask for
+#[derive(Debug, PartialEq)]
+enum Command {
+    Repl,
+    Version,
+    Help,
+    Eval(String),
+    File(String),
+    // the arguments are wrong, the message says why
+    Usage(String)
+}
+
+fn dispatch(args: &[String]) -> Command {
+    match args.get(1).map(|arg| arg.as_str()) {
+        None => Command::Repl,
+        Some("-v" | "--version") => Command::Version,
+        Some("-h" | "--help") => Command::Help,
+        Some(flag @ ("-e" | "--eval")) => match args.get(2) {
+            Some(code) => Command::Eval(code.to_owned()),
+            None => Command::Usage(format!("{} expects code to run", flag))
+        },
+        Some(filename) => Command::File(filename.to_owned())
+    }
+}
+
+fn run() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 2 {
-        run_repl()
+    match dispatch(&args) {
+        Command::Repl => run_repl(),
+        Command::Version => println!("coco {}", env!("CARGO_PKG_VERSION")),
+        Command::Help => println!("{}", USAGE),
+        Command::Eval(code) => run_code("<eval>".to_string(), code),
+        Command::File(filename) => run_file(filename),
+        Command::Usage(msg) => {
+            error_message(format!("{}\n{}", msg, USAGE));
+            exit(-1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dispatch, eval, Command, ErrorKind, LexError, ParseError, Resolver, RuntimeError};
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
     }
 
-    let filename = &args[1];
-    run_file(filename.to_owned());
+    #[test]
+    fn dispatches_arguments() {
+        assert_eq!(dispatch(&args(&["coco"])), Command::Repl);
+        assert_eq!(dispatch(&args(&["coco", "-v"])), Command::Version);
+        assert_eq!(dispatch(&args(&["coco", "--help"])), Command::Help);
+        assert_eq!(dispatch(&args(&["coco", "-e", "log(1)"])), Command::Eval("log(1)".to_string()));
+        assert_eq!(dispatch(&args(&["coco", "script.co", "--eval"])), Command::File("script.co".to_string()));
+    }
+
+    #[test]
+    fn eval_without_code_is_a_usage_error() {
+        assert_eq!(dispatch(&args(&["coco", "--eval"])), Command::Usage("--eval expects code to run".to_string()));
+    }
+
+    #[test]
+    fn later_input_resolves_to_session_lines() {
+        let resolver = Resolver::after("<repl>".to_string(), "fun f(x) {\n  return x()\n}\n", "f(5)\n");
+
+        // the `f` of `f(5)` is on the fourth line of the session
+        assert_eq!(resolver.resolve_where(0), vec![4, 1]);
+        assert!(resolver.show_line(&[2, 11]).unwrap().contains("return x()"));
+    }
+
+    #[test]
+    fn errors_tell_where_they_come_from() {
+        let kind = |code: &str| eval(code).err().map(|e| e.kind());
+
+        assert_eq!(kind("let s = 'open"), Some(ErrorKind::Lex(LexError::UnterminatedString)));
+        assert_eq!(kind("if (true { }"), Some(ErrorKind::Parse(ParseError::UnexpectedToken)));
+        assert_eq!(kind("let x = 5\nx()"), Some(ErrorKind::Runtime(RuntimeError::NotAFunction)));
+        assert_eq!(kind("throw 'up'"), Some(ErrorKind::Runtime(RuntimeError::Uncaught)));
+    }
 }
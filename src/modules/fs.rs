@@ -0,0 +1,81 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}};
+
+use super::CocoModule;
+
+pub struct FSModule {}
+
+impl CocoModule for FSModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([ 
+            ("readFile".to_string(), Box::new(get_read_file())),
+            ("writeFile".to_string(), Box::new(get_write_file())),
+            ("exists".to_string(), Box::new(get_exists())),
+            ("readDir".to_string(), Box::new(get_read_dir()))
+        ])
+    }
+}
+
+// null when the file can't be read
+fn get_read_file() -> Value {
+    Value::Function(
+        "readFile".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("path".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let path = args.get("path").unwrap().as_string();
+
+            Ok(fs::read_to_string(path).map(Value::String).unwrap_or(Value::Null))
+        }
+    ))
+}
+
+// returns whether the file was written
+fn get_write_file() -> Value {
+    Value::Function(
+        "writeFile".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("path".to_string()), FunctionArgument::Required("contents".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let path = args.get("path").unwrap().as_string();
+            let contents = args.get("contents").unwrap().as_string();
+
+            Ok(Value::Boolean(fs::write(path, contents).is_ok()))
+        }
+    ))
+}
+
+fn get_exists() -> Value {
+    Value::Function(
+        "exists".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("path".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let path = args.get("path").unwrap().as_string();
+
+            Ok(Value::Boolean(Path::new(&path).exists()))
+        }
+    ))
+}
+
+// sorted entry names, null when the directory can't be read
+fn get_read_dir() -> Value {
+    Value::Function(
+        "readDir".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("path".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let path = args.get("path").unwrap().as_string();
+
+            let entries = match fs::read_dir(path) {
+                Ok(entries) => entries,
+                Err(_e) => return Ok(Value::Null)
+            };
+
+            let mut names = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+            names.sort();
+
+            Ok(Value::Array(names.into_iter().map(|name| Box::new(Value::String(name))).collect()))
+        }
+    ))
+}
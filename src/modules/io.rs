@@ -1,27 +1,70 @@
-use std::{collections::BTreeMap, io::{ self, Write }, env};
+use std::{collections::BTreeMap, io::{ self, BufRead, Read, Write }, env, sync::Mutex};
 
-use crate::interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}};
+use lazy_static::lazy_static;
+
+use crate::{interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument, parse_number}}, parser::Node, Error, RuntimeError};
 
 use super::CocoModule;
 
+lazy_static! {
+    static ref OUTPUT: Mutex<Output> = Mutex::new(Output { buffered: false, pending: String::new() });
+}
+
+// while buffered, written text is kept until flush() or a read
+struct Output {
+    buffered: bool,
+    pending: String
+}
+
+pub fn write_output(text: &str) {
+    let mut output = OUTPUT.lock().unwrap();
+
+    if output.buffered {
+        output.pending.push_str(text);
+        return
+    }
+
+    print!("{}", text);
+    let _ = io::stdout().flush();
+}
+
+pub fn flush_output() {
+    let mut output = OUTPUT.lock().unwrap();
+
+    print!("{}", output.pending);
+    output.pending.clear();
+    let _ = io::stdout().flush();
+}
+
 pub struct IOModule {}
 
 impl CocoModule for IOModule {
     fn get() -> BTreeMap<String, Box<Value>> {
         BTreeMap::from([ 
             ("argv".to_string(), Box::new(get_argv())),
+            ("flush".to_string(), Box::new(get_flush())),
+            ("confirm".to_string(), Box::new(get_confirm())),
+            ("error".to_string(), Box::new(get_error())),
+            ("format".to_string(), Box::new(get_format())),
+            ("prompt".to_string(), Box::new(get_prompt())),
+            ("print".to_string(), Box::new(get_print())),
+            ("promptNumber".to_string(), Box::new(get_prompt_number())),
             ("read".to_string(), Box::new(get_read())),
+            ("readAll".to_string(), Box::new(get_read_all())),
+            ("setBuffered".to_string(), Box::new(get_set_buffered())),
+            ("stderr".to_string(), Box::new(get_stderr())),
             ("stdin".to_string(), Box::new(get_stdin())),
-            ("stdout".to_string(), Box::new(get_stdout()))
+            ("stdout".to_string(), Box::new(get_stdout())),
+            ("write".to_string(), Box::new(get_write_raw()))
         ])
     }
 }
 
+// the arguments after the script name, empty in the repl
 fn get_argv() -> Value {
     Value::Array(
         env::args()
-        .collect::<Vec<String>>()
-        .drain(2..)
+        .skip(2)
         .map(|s| Box::new(Value::String(s)))
         .collect::<Vec<Box<Value>>>()
     )
@@ -30,7 +73,8 @@ fn get_argv() -> Value {
 fn get_stdin() -> Value {
     Value::Object(
         BTreeMap::from([ 
-            ("read".to_string(), Box::new(get_read())) 
+            ("read".to_string(), Box::new(get_read())),
+            ("readAll".to_string(), Box::new(get_read_all()))
         ])
     )
 }
@@ -40,6 +84,9 @@ fn get_read() -> Value {
         "read".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Spread("vals".to_string())])), 
         FuncImpl::Builtin(|args| {
+            // everything written so far has to be visible before blocking on input
+            flush_output();
+
             if let Value::Array(vals) = args.get("vals").unwrap() {
                 for val in vals {
                     match *val.to_owned() {
@@ -51,13 +98,117 @@ fn get_read() -> Value {
             let _ = io::stdout().flush();
             let mut buffer = String::new();
             if let Ok(_b) = io::stdin().read_line(&mut buffer) {   
-                return Value::String(buffer.trim_end().to_string())
+                return Ok(Value::String(buffer.trim_end().to_string()))
             }
-            Value::Null
+            Ok(Value::Null)
+        })
+    )
+}
+
+// the rest of stdin up to EOF as one string, '' when nothing is left
+fn get_read_all() -> Value {
+    Value::Function(
+        "readAll".to_owned(),
+        FunctionArguments::new(Vec::new()),
+        FuncImpl::Builtin(|_args| {
+            read_all(&mut io::stdin().lock())
         })
     )
 }
 
+fn read_all(input: &mut impl Read) -> Result<Value, Error> {
+    flush_output();
+
+    let mut buffer = String::new();
+    if let Err(e) = input.read_to_string(&mut buffer) {
+        return Err(Error::runtime(RuntimeError::Io, format!("Cannot read stdin: {}", e)))
+    }
+
+    Ok(Value::String(buffer))
+}
+
+// shows the message and reads one line without its line break, None at the end of input
+fn prompt_line(message: &Value, input: &mut impl BufRead) -> Option<String> {
+    flush_output();
+
+    match message {
+        Value::String(s) => print!("{} ", s),
+        Value::Null => {},
+        _ => print!("{} ", message)
+    }
+    let _ = io::stdout().flush();
+
+    let mut buffer = String::new();
+    match input.read_line(&mut buffer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(buffer.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+// whole answers that fit are integers, like number literals
+fn parse_prompt_number(answer: &str) -> Option<Value> {
+    let answer = answer.trim();
+    let num = parse_number(answer)?;
+
+    match answer.parse::<i64>() {
+        Ok(int) => Some(Value::Integer(int)),
+        Err(_) => Some(Value::Number(num))
+    }
+}
+
+// y, yes, n and no in any case, anything else isn't an answer
+fn parse_confirm(answer: &str) -> Option<bool> {
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None
+    }
+}
+
+fn get_prompt() -> Value {
+    Value::Function(
+        "prompt".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::NotRequired("message".to_string(), Node::Null)])),
+        FuncImpl::Builtin(|args| {
+            Ok(prompt_line(args.get("message").unwrap(), &mut io::stdin().lock()).map(Value::String).unwrap_or(Value::Null))
+        }
+    ))
+}
+
+// asks again until the answer is a number, null at the end of input
+fn get_prompt_number() -> Value {
+    Value::Function(
+        "promptNumber".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::NotRequired("message".to_string(), Node::Null)])),
+        FuncImpl::Builtin(|args| {
+            while let Some(answer) = prompt_line(args.get("message").unwrap(), &mut io::stdin().lock()) {
+                if let Some(num) = parse_prompt_number(&answer) {
+                    return Ok(num)
+                }
+            }
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+// asks again until the answer is yes or no, false at the end of input
+fn get_confirm() -> Value {
+    Value::Function(
+        "confirm".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::NotRequired("message".to_string(), Node::Null)])),
+        FuncImpl::Builtin(|args| {
+            while let Some(answer) = prompt_line(args.get("message").unwrap(), &mut io::stdin().lock()) {
+                if let Some(confirmed) = parse_confirm(&answer) {
+                    return Ok(Value::Boolean(confirmed))
+                }
+            }
+
+            Ok(Value::Boolean(false))
+        }
+    ))
+}
+
 fn get_stdout() -> Value {
     Value::Object(
         BTreeMap::from([ 
@@ -66,22 +217,395 @@ fn get_stdout() -> Value {
     )
 }
 
+// the way log() writes its arguments: each one followed by a space, then a line break
+fn format_line(vals: &Value) -> String {
+    let mut line = String::new();
+
+    if let Value::Array(vals) = vals {
+        for val in vals {
+            match *val.to_owned() {
+                Value::String(s) => line.push_str(&format!("{} ", s)),
+                _ => line.push_str(&format!("{} ", val))
+            }
+        }
+    }
+
+    line.push('\n');
+    line
+}
+
 pub fn get_write() -> Value {
     Value::Function(
         "write".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Spread("vals".to_string())])), 
         FuncImpl::Builtin(|args| {
-            if let Value::Array(vals) = args.get("vals").unwrap() {
-                for val in vals {
-                    match *val.to_owned() {
-                        Value::String(s) => print!("{} ", s),
-                        _ => print!("{} ", val)
-                    }
+            write_output(&format_line(args.get("vals").unwrap()));
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+// the arguments with `separator` between them, nothing after the last one
+fn join_values(vals: &Value, separator: &str) -> String {
+    match vals {
+        Value::Array(vals) => vals.iter().map(|val| match val.as_ref() {
+            Value::String(s) => s.to_owned(),
+            val => val.to_string()
+        }).collect::<Vec<String>>().join(separator),
+        _ => String::new()
+    }
+}
+
+// like log(), without the trailing space and line break
+pub fn get_print() -> Value {
+    Value::Function(
+        "print".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Spread("vals".to_string())])), 
+        FuncImpl::Builtin(|args| {
+            write_output(&join_values(args.get("vals").unwrap(), " "));
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+// the arguments exactly as they are, nothing between or after them
+pub fn get_write_raw() -> Value {
+    Value::Function(
+        "write".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Spread("vals".to_string())])), 
+        FuncImpl::Builtin(|args| {
+            write_output(&join_values(args.get("vals").unwrap(), ""));
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+fn get_stderr() -> Value {
+    Value::Object(
+        BTreeMap::from([ 
+            ("write".to_string(), Box::new(get_error())) 
+        ])
+    )
+}
+
+// like log(), but to stderr. stdout is flushed first so the two stay in order
+fn get_error() -> Value {
+    Value::Function(
+        "error".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Spread("vals".to_string())])), 
+        FuncImpl::Builtin(|args| {
+            write_error(&mut io::stderr(), args.get("vals").unwrap());
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+fn write_error(errors: &mut impl Write, vals: &Value) {
+    flush_output();
+
+    let _ = errors.write_all(format_line(vals).as_bytes());
+    let _ = errors.flush();
+}
+
+pub fn get_flush() -> Value {
+    Value::Function(
+        "flush".to_owned(),
+        FunctionArguments::new(Vec::new()),
+        FuncImpl::Builtin(|_args| {
+            flush_output();
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+fn get_set_buffered() -> Value {
+    Value::Function(
+        "setBuffered".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("buffered".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let buffered = args.get("buffered").unwrap().as_bool();
+
+            if !buffered {
+                flush_output();
+            }
+
+            OUTPUT.lock().unwrap().buffered = buffered;
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+// placeholder spec: [[fill]align][0][width][.precision], e.g. {:>8}, {:.2}, {:05}
+struct FormatSpec {
+    fill: char,
+    align: Option<char>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>
+}
+
+impl FormatSpec {
+    fn parse(spec: &str) -> Result<Self, Error> {
+        let chars = spec.chars().collect::<Vec<char>>();
+        let mut result = Self { fill: ' ', align: None, zero: false, width: 0, precision: None };
+        let mut i = 0;
+
+        if chars.len() > 1 && "<>^".contains(chars[1]) {
+            result.fill = chars[0];
+            result.align = Some(chars[1]);
+            i = 2;
+        } else if !chars.is_empty() && "<>^".contains(chars[0]) {
+            result.align = Some(chars[0]);
+            i = 1;
+        }
+
+        if chars.get(i) == Some(&'0') {
+            result.zero = true;
+            i += 1;
+        }
+
+        let width = chars[i..].iter().take_while(|c| c.is_ascii_digit()).collect::<String>();
+        i += width.len();
+        result.width = width.parse().unwrap_or(0);
+
+        if chars.get(i) == Some(&'.') {
+            let precision = chars[i + 1..].iter().take_while(|c| c.is_ascii_digit()).collect::<String>();
+            if precision.is_empty() {
+                return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Unknown format spec: {}", spec)))
+            }
+            i += precision.len() + 1;
+            result.precision = precision.parse().ok();
+        }
+
+        if i != chars.len() {
+            return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Unknown format spec: {}", spec)))
+        }
+
+        Ok(result)
+    }
+
+    fn apply(&self, value: &Value) -> String {
+        let text = match (value, self.precision) {
+            (Value::Integer(num), Some(precision)) => format!("{:.*}", precision, *num as f64),
+            (Value::Number(num), Some(precision)) => format!("{:.*}", precision, num),
+            (Value::String(string), Some(precision)) => string.chars().take(precision).collect(),
+            _ => value.as_string()
+        };
+
+        let len = text.chars().count();
+        if len >= self.width {
+            return text
+        }
+        let padding = self.width - len;
+
+        // zero padding goes between the sign and the digits
+        if self.zero && self.align.is_none() {
+            if let Value::Integer(_) | Value::Number(_) = value {
+                let (sign, digits) = match text.strip_prefix('-') {
+                    Some(digits) => ("-", digits),
+                    None => ("", text.as_str())
+                };
+                return format!("{}{}{}", sign, "0".repeat(padding), digits)
+            }
+        }
+
+        let default_align = match value {
+            Value::Integer(_) | Value::Number(_) => '>',
+            _ => '<'
+        };
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+
+        match self.align.unwrap_or(default_align) {
+            '>' => format!("{}{}", fill(padding), text),
+            '^' => format!("{}{}{}", fill(padding / 2), text, fill(padding - padding / 2)),
+            _ => format!("{}{}", text, fill(padding))
+        }
+    }
+}
+
+fn get_format() -> Value {
+    Value::Function(
+        "format".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("template".to_string()), FunctionArgument::Spread("vals".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let template = args.get("template").unwrap().as_string();
+            let vals = match args.get("vals").unwrap() {
+                Value::Array(vals) => vals.to_owned(),
+                _ => vec![]
+            };
+
+            let mut result = String::new();
+            let mut next = 0;
+            let mut chars = template.chars().peekable();
+
+            while let Some(current) = chars.next() {
+                match current {
+                    '{' if chars.peek() == Some(&'{') => {
+                        chars.next();
+                        result.push('{');
+                    },
+                    '}' if chars.peek() == Some(&'}') => {
+                        chars.next();
+                        result.push('}');
+                    },
+                    '{' => {
+                        let mut placeholder = String::new();
+                        let mut closed = false;
+                        for c in chars.by_ref() {
+                            if c == '}' {
+                                closed = true;
+                                break
+                            }
+                            placeholder.push(c);
+                        }
+
+                        if !closed {
+                            return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Unclosed placeholder: {{{}", placeholder)))
+                        }
+
+                        let spec = match placeholder.strip_prefix(':') {
+                            Some(spec) => spec,
+                            None if placeholder.is_empty() => "",
+                            None => return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Unknown format spec: {}", placeholder)))
+                        };
+
+                        let value = vals.get(next).map(|v| *v.to_owned()).unwrap_or(Value::Null);
+                        next += 1;
+
+                        result.push_str(&FormatSpec::parse(spec)?.apply(&value));
+                    },
+                    _ => result.push(current)
                 }
-                println!()
             }
 
-            Value::Null
+            Ok(Value::String(result))
         }
     ))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, interpreter::types::Value};
+
+    use std::{io::Cursor, sync::Mutex};
+
+    use super::{parse_confirm, parse_prompt_number, prompt_line, read_all, write_error, write_output, OUTPUT};
+
+    // tests that switch the shared output to buffered take turns
+    static BUFFERING: Mutex<()> = Mutex::new(());
+
+    // what the code writes, caught by buffering the output
+    fn output_of(code: &str) -> String {
+        let _buffering = BUFFERING.lock().unwrap();
+        OUTPUT.lock().unwrap().buffered = true;
+        let result = eval(code);
+
+        let mut output = OUTPUT.lock().unwrap();
+        output.buffered = false;
+        result.unwrap();
+
+        std::mem::take(&mut output.pending)
+    }
+
+    #[test]
+    fn print_and_write_output_exactly_their_arguments() {
+        assert_eq!(output_of("print('a', 'b')"), "a b");
+        assert_eq!(output_of("print(1, [2], null)"), "1 [ 2 ] null");
+        assert_eq!(output_of("write('a', 'b', 3)"), "ab3");
+        assert_eq!(output_of("write()"), "");
+        // log keeps its trailing space and line break
+        assert_eq!(output_of("log('a', 'b')"), "a b \n");
+    }
+
+    #[test]
+    fn confirm_answers() {
+        for yes in ["y", "Y", "yes", "YES", " Yes\n"] {
+            assert_eq!(parse_confirm(yes), Some(true));
+        }
+        for no in ["n", "N", "no", "No", "nO\r\n"] {
+            assert_eq!(parse_confirm(no), Some(false));
+        }
+        for other in ["", "yep", "nope", "1", "true"] {
+            assert_eq!(parse_confirm(other), None);
+        }
+    }
+
+    fn format(args: &str) -> Result<Value, crate::Error> {
+        eval(&format!("import format from 'io'\nlet result = format({})", args)).map(|scope| scope.get("result".to_string()))
+    }
+
+    #[test]
+    fn format_fills_placeholders() {
+        assert_eq!(format("'{} and {:>4}', 1, 'x'").unwrap(), Value::String("1 and    x".to_string()));
+        assert_eq!(format("'{{}} {:05.1}', 3.14159").unwrap(), Value::String("{} 003.1".to_string()));
+    }
+
+    #[test]
+    fn malformed_placeholders_are_errors() {
+        assert!(format("'x {', 1").is_err());
+        assert!(format("'x {:>4', 1").is_err());
+        assert!(format("'{:q}', 1").is_err());
+        assert!(format("'{name}', 1").is_err());
+    }
+
+    #[test]
+    fn prompt_number_answers() {
+        assert_eq!(parse_prompt_number("3"), Some(Value::Integer(3)));
+        assert_eq!(parse_prompt_number(" -12\n"), Some(Value::Integer(-12)));
+        assert_eq!(parse_prompt_number("2.5"), Some(Value::Number(2.5)));
+        assert_eq!(parse_prompt_number("1e3"), Some(Value::Number(1000.0)));
+        // too big for an integer
+        assert_eq!(parse_prompt_number("99999999999999999999"), Some(Value::Number(1e20)));
+        for other in ["", "abc", "12x", "inf"] {
+            assert_eq!(parse_prompt_number(other), None);
+        }
+    }
+
+    #[test]
+    fn buffered_output_is_flushed_before_reading() {
+        let _buffering = BUFFERING.lock().unwrap();
+        OUTPUT.lock().unwrap().buffered = true;
+        write_output("progress");
+        let pending = OUTPUT.lock().unwrap().pending.clone();
+
+        let answer = prompt_line(&Value::Null, &mut Cursor::new("answer\r\nnext\n"));
+
+        let mut output = OUTPUT.lock().unwrap();
+        output.buffered = false;
+
+        assert_eq!(pending, "progress");
+        assert_eq!(output.pending, "");
+        assert_eq!(answer, Some("answer".to_string()));
+    }
+
+    #[test]
+    fn read_all_returns_every_line() {
+        let text = "first\nsecond\r\n\nlast without a break";
+
+        assert_eq!(read_all(&mut Cursor::new(text)).unwrap(), Value::String(text.to_string()));
+        assert_eq!(read_all(&mut Cursor::new("")).unwrap(), Value::String(String::new()));
+    }
+
+    #[test]
+    fn errors_are_written_like_log() {
+        let _buffering = BUFFERING.lock().unwrap();
+        OUTPUT.lock().unwrap().buffered = true;
+        write_output("before");
+
+        let mut errors = vec![];
+        write_error(&mut errors, &Value::Array(vec![Box::new(Value::String("failed:".to_string())), Box::new(Value::Integer(3))]));
+
+        let mut output = OUTPUT.lock().unwrap();
+        output.buffered = false;
+
+        assert_eq!(String::from_utf8(errors).unwrap(), "failed: 3 \n");
+        // stdout written before the error went out first
+        assert_eq!(output.pending, "");
+    }
+}
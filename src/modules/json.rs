@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use crate::{interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}}, Error, RuntimeError};
+
+use super::CocoModule;
+
+pub struct JSONModule {}
+
+impl CocoModule for JSONModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([ 
+            ("parse".to_string(), Box::new(get_parse())),
+            ("stringify".to_string(), Box::new(get_stringify()))
+        ])
+    }
+}
+
+// malformed input is an error naming the character parsing stopped at
+fn get_parse() -> Value {
+    Value::Function(
+        "parse".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("string".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let string = args.get("string").unwrap().as_string();
+
+            let mut parser = JSONParser::new(&string);
+
+            match parser.parse() {
+                Some(value) => Ok(value),
+                None => Err(Error::runtime(RuntimeError::Json, format!("Invalid JSON at character {}", parser.pos.min(parser.chars.len()) + 1)))
+            }
+        }
+    ))
+}
+
+fn get_stringify() -> Value {
+    Value::Function(
+        "stringify".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("value".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::String(stringify(args.get("value").unwrap())))
+        }
+    ))
+}
+
+// values JSON can't represent (functions, classes, NaN) become null
+pub fn stringify(value: &Value) -> String {
+    match value {
+        Value::String(val) => quote(val),
+        Value::Integer(val) => val.to_string(),
+        Value::Number(val) if val.is_finite() => val.to_string(),
+        Value::Boolean(val) => val.to_string(),
+        Value::Array(values) => format!("[{}]", values.iter().map(|x| stringify(x)).collect::<Vec<_>>().join(",")),
+        Value::Object(map) => format!(
+            "{{{}}}",
+            map.iter().map(|(key, value)| format!("{}:{}", quote(key), stringify(value))).collect::<Vec<_>>().join(",")
+        ),
+        // instances are written as objects of their fields
+        Value::Instance(_, fields) => stringify(&Value::Object(
+            fields.iter().filter(|(_, value)| !matches!(***value, Value::Function(..))).map(|(key, value)| (key.to_owned(), value.to_owned())).collect()
+        )),
+        _ => "null".to_string()
+    }
+}
+
+fn quote(string: &str) -> String {
+    let mut quoted = String::from('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+struct JSONParser {
+    chars: Vec<char>,
+    pos: usize
+}
+
+impl JSONParser {
+    fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            pos: 0
+        }
+    }
+
+    // the whole input has to be a single value
+    fn parse(&mut self) -> Option<Value> {
+        let value = self.value()?;
+        self.skip_whitespace();
+
+        match self.pos == self.chars.len() {
+            true => Some(value),
+            false => None
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        match self.next() {
+            Some(c) if c == expected => Some(()),
+            _ => None
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\n' | '\r' | '\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Option<Value> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+
+        Some(value)
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.skip_whitespace();
+
+        match self.peek()? {
+            '{' => self.object(),
+            '[' => self.array(),
+            '"' => self.string().map(Value::String),
+            't' => self.keyword("true", Value::Boolean(true)),
+            'f' => self.keyword("false", Value::Boolean(false)),
+            'n' => self.keyword("null", Value::Null),
+            '-' | '0'..='9' => self.number(),
+            _ => None
+        }
+    }
+
+    fn object(&mut self) -> Option<Value> {
+        self.expect('{')?;
+        let mut map = BTreeMap::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Some(Value::Object(map))
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            map.insert(key, Box::new(self.value()?));
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Some(Value::Object(map)),
+                _ => return None
+            }
+        }
+    }
+
+    fn array(&mut self) -> Option<Value> {
+        self.expect('[')?;
+        let mut values = vec![];
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Some(Value::Array(values))
+        }
+
+        loop {
+            values.push(Box::new(self.value()?));
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Some(Value::Array(values)),
+                _ => return None
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            match self.next()? {
+                '"' => return Some(string),
+                '\\' => {
+                    let escaped = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode_escape()?,
+                        _ => return None
+                    };
+
+                    string.push(escaped);
+                },
+                c => string.push(c)
+            }
+        }
+    }
+
+    // \uXXXX. characters past U+FFFF are written as a surrogate pair of two
+    // escapes, a surrogate on its own isn't a character
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high)
+        }
+
+        self.expect('\\')?;
+        self.expect('u')?;
+        let low = self.hex()?;
+
+        match (0xDC00..0xE000).contains(&low) {
+            true => char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)),
+            false => None
+        }
+    }
+
+    fn hex(&mut self) -> Option<u32> {
+        let hex = self.chars.get(self.pos..self.pos + 4)?.iter().collect::<String>();
+        self.pos += 4;
+
+        // from_str_radix would also take a sign
+        match hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            true => u32::from_str_radix(&hex, 16).ok(),
+            false => None
+        }
+    }
+
+    fn number(&mut self) -> Option<Value> {
+        let start = self.pos;
+
+        while let Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+
+        let text = self.chars[start..self.pos].iter().collect::<String>();
+
+        // no leading zeros, 0 and 0.5 are fine but 01 isn't
+        let digits = text.strip_prefix('-').unwrap_or(&text).as_bytes();
+        if digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit() {
+            return None
+        }
+
+        match text.parse::<i64>() {
+            Ok(value) => Some(Value::Integer(value)),
+            Err(_) => text.parse::<f64>().ok().map(Value::Number)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interpreter::types::Value, ErrorKind, RuntimeError};
+
+    use super::JSONParser;
+
+    fn parse(text: &str) -> Option<Value> {
+        JSONParser::new(text).parse()
+    }
+
+    #[test]
+    fn parses_nested_values() {
+        assert_eq!(parse(r#" {"a": [1, 2.5, null], "b": {"c": true}} "#).map(|value| super::stringify(&value)), Some(r#"{"a":[1,2.5,null],"b":{"c":true}}"#.to_string()));
+    }
+
+    #[test]
+    fn surrogate_pairs_are_one_character() {
+        assert_eq!(parse(r#""\ud83d\ude00""#), Some(Value::String("\u{1F600}".to_string())));
+        assert_eq!(parse(r#""\u00e9""#), Some(Value::String("é".to_string())));
+        assert_eq!(parse(r#""\ud83d""#), None);
+        assert_eq!(parse(r#""\ud83dx""#), None);
+        assert_eq!(parse(r#""\ude00""#), None);
+        assert_eq!(parse(r#""\u+123""#), None);
+    }
+
+    #[test]
+    fn leading_zeros_are_rejected() {
+        assert_eq!(parse("0"), Some(Value::Integer(0)));
+        assert_eq!(parse("-0.5"), Some(Value::Number(-0.5)));
+        assert_eq!(parse("01"), None);
+        assert_eq!(parse("-01"), None);
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        let result = crate::eval("
+            import * as json from 'json'
+            json.parse('[1, 2')
+        ");
+
+        assert_eq!(result.err().map(|e| e.kind()), Some(ErrorKind::Runtime(RuntimeError::Json)));
+    }
+}
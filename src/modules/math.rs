@@ -1,28 +1,59 @@
-use std::{collections::BTreeMap, f64::consts::PI};
-use rand::{ thread_rng, Rng };
+use std::{collections::BTreeMap, f64::consts::{PI, E, TAU}, sync::Mutex};
 
-use crate::interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}};
+use lazy_static::lazy_static;
+use rand::{ rngs::StdRng, Rng, SeedableRng };
+
+use crate::{interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}}, Error, RuntimeError};
 
 use super::CocoModule;
 
+lazy_static! {
+    // shared so that seed() makes every later random call reproducible
+    static ref RNG: Mutex<StdRng> = Mutex::new(StdRng::from_entropy());
+}
+
+// `amount` different positions out of 0..len in random order, drawn from the seedable RNG
+pub fn sample_indices(len: usize, amount: usize) -> Vec<usize> {
+    rand::seq::index::sample(&mut *RNG.lock().unwrap(), len, amount).into_vec()
+}
+
 pub struct MathModule {}
 
 impl CocoModule for MathModule {
     fn get() -> BTreeMap<String, Box<Value>> {
         BTreeMap::from([ 
             ("PI".to_string(), Box::new(Value::Number(PI))),
+            ("E".to_string(), Box::new(Value::Number(E))),
+            ("TAU".to_string(), Box::new(Value::Number(TAU))),
+            ("INFINITY".to_string(), Box::new(Value::Number(f64::INFINITY))),
 
             ("pow".to_string(), Box::new(get_pow())),
             ("abs".to_string(), Box::new(get_abs())),
             ("ceil".to_string(), Box::new(get_ceil())),
             ("floor".to_string(), Box::new(get_floor())),
             ("round".to_string(), Box::new(get_round())),
+            ("roundTo".to_string(), Box::new(get_round_to())),
             ("random".to_string(), Box::new(get_random())),
+            ("randomInt".to_string(), Box::new(get_random_int())),
+            ("seed".to_string(), Box::new(get_seed())),
             ("max".to_string(), Box::new(get_max())),
             ("min".to_string(), Box::new(get_min())),
             ("sin".to_string(), Box::new(get_sin())),
             ("cos".to_string(), Box::new(get_cos())),
-            ("tan".to_string(), Box::new(get_tan()))
+            ("tan".to_string(), Box::new(get_tan())),
+            ("sqrt".to_string(), Box::new(get_sqrt())),
+            ("cbrt".to_string(), Box::new(get_cbrt())),
+            ("log".to_string(), Box::new(get_log())),
+            ("log10".to_string(), Box::new(get_log10())),
+            ("log2".to_string(), Box::new(get_log2())),
+            ("exp".to_string(), Box::new(get_exp())),
+            ("sign".to_string(), Box::new(get_sign())),
+            ("trunc".to_string(), Box::new(get_trunc())),
+            ("divmod".to_string(), Box::new(get_divmod())),
+            ("mod".to_string(), Box::new(get_mod())),
+            ("smoothstep".to_string(), Box::new(get_smoothstep())),
+            ("easeInQuad".to_string(), Box::new(get_ease_in_quad())),
+            ("easeOutQuad".to_string(), Box::new(get_ease_out_quad()))
         ])
     }
 }
@@ -32,7 +63,7 @@ fn get_pow() -> Value {
         "pow".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string()), FunctionArgument::Required("pow".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().powf(args.get("pow").unwrap().as_number()))
+            Ok(Value::Number(args.get("num").unwrap().as_number().powf(args.get("pow").unwrap().as_number())))
         }
     ))
 }
@@ -42,17 +73,30 @@ fn get_abs() -> Value {
         "abs".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().abs())
+            Ok(Value::Number(args.get("num").unwrap().as_number().abs()))
         }
     ))
 }
 
+// rounding results drop the sign of zero, so ceil(-0.5) indexes like 0
+fn integral(num: f64) -> f64 {
+    num + 0.0
+}
+
+// a rounded result is an integer when it fits, so it indexes exactly
+fn whole(num: f64) -> Value {
+    match (i64::MIN as f64..i64::MAX as f64).contains(&num) {
+        true => Value::Integer(num as i64),
+        false => Value::Number(integral(num))
+    }
+}
+
 fn get_ceil() -> Value {
     Value::Function(
         "ceil".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().ceil())
+            Ok(whole(args.get("num").unwrap().as_number().ceil()))
         }
     ))
 }
@@ -62,7 +106,7 @@ fn get_floor() -> Value {
         "floor".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().floor())
+            Ok(whole(args.get("num").unwrap().as_number().floor()))
         }
     ))
 }
@@ -72,18 +116,75 @@ fn get_round() -> Value {
         "round".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().round())
+            Ok(whole(args.get("num").unwrap().as_number().round()))
         }
     ))
 }
 
+// the nearest multiple of step, halfway values go away from zero: roundTo(7.5, 5) is 10
+fn get_round_to() -> Value {
+    Value::Function(
+        "roundTo".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string()), FunctionArgument::Required("step".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let num = args.get("num").unwrap().as_number();
+            let step = args.get("step").unwrap().as_number();
+
+            if step.is_nan() || step <= 0.0 {
+                return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Step must be greater than 0, got {}", Value::Number(step).as_string())))
+            }
+
+            Ok(Value::Number(integral((num / step).round() * step)))
+        }
+    ))
+}
+
+// random() is in [0, 1), random(min, max) in [min, max)
 fn get_random() -> Value {
     Value::Function(
         "random".to_owned(),
-        FunctionArguments::new(Vec::from([FunctionArgument::Spread("".to_string())])), 
-        FuncImpl::Builtin(|_| {
-            let mut rng = thread_rng();
-            Value::Number(rng.gen())
+        FunctionArguments::new(Vec::from([FunctionArgument::Spread("range".to_string())])), 
+        FuncImpl::Builtin(|args| {
+            let unit: f64 = RNG.lock().unwrap().gen();
+
+            match args.get("range").unwrap() {
+                Value::Array(range) if range.len() >= 2 => {
+                    let (min, max) = (range[0].as_number(), range[1].as_number());
+
+                    Ok(Value::Number(min + unit * (max - min)))
+                },
+                _ => Ok(Value::Number(unit))
+            }
+        }
+    ))
+}
+
+// an integer in [min, max), an empty range is an error
+fn get_random_int() -> Value {
+    Value::Function(
+        "randomInt".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("min".to_string()), FunctionArgument::Required("max".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let min = args.get("min").unwrap().as_number().ceil();
+            let max = args.get("max").unwrap().as_number().ceil();
+
+            if min >= max || !min.is_finite() || !max.is_finite() {
+                return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Empty range for randomInt: [{}, {})", Value::Number(min).as_string(), Value::Number(max).as_string())))
+            }
+
+            Ok(Value::Integer(RNG.lock().unwrap().gen_range(min as i64..max as i64)))
+        }
+    ))
+}
+
+fn get_seed() -> Value {
+    Value::Function(
+        "seed".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            *RNG.lock().unwrap() = StdRng::seed_from_u64(args.get("num").unwrap().as_number() as u64);
+
+            Ok(Value::Null)
         }
     ))
 }
@@ -93,11 +194,10 @@ fn get_max() -> Value {
         "max".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num1".to_string()), FunctionArgument::Required("num2".to_string())])), 
         FuncImpl::Builtin(|args| {
-            args
+            Ok(args
             .into_values()
-            .into_iter()
             .max_by(|v1, v2| v1.as_number().total_cmp(&v2.as_number()))
-            .unwrap_or(Value::Null)
+            .unwrap_or(Value::Null))
         }
     ))
 }
@@ -107,11 +207,10 @@ fn get_min() -> Value {
         "min".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num1".to_string()), FunctionArgument::Required("num2".to_string())])), 
         FuncImpl::Builtin(|args| {
-            args
+            Ok(args
             .into_values()
-            .into_iter()
             .min_by(|v1, v2| v1.as_number().total_cmp(&v2.as_number()))
-            .unwrap_or(Value::Null)
+            .unwrap_or(Value::Null))
         }
     ))
 }
@@ -121,7 +220,7 @@ fn get_sin() -> Value {
         "sin".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().sin())
+            Ok(Value::Number(args.get("num").unwrap().as_number().sin()))
         }
     ))
 }
@@ -131,7 +230,7 @@ fn get_cos() -> Value {
         "cos".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().cos())
+            Ok(Value::Number(args.get("num").unwrap().as_number().cos()))
         }
     ))
 }
@@ -141,7 +240,307 @@ fn get_tan() -> Value {
         "tan".to_owned(),
         FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
         FuncImpl::Builtin(|args| {
-            Value::Number(args.get("num").unwrap().as_number().tan())
+            Ok(Value::Number(args.get("num").unwrap().as_number().tan()))
+        }
+    ))
+}
+
+fn get_sqrt() -> Value {
+    Value::Function(
+        "sqrt".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Number(args.get("num").unwrap().as_number().sqrt()))
+        }
+    ))
+}
+
+fn get_cbrt() -> Value {
+    Value::Function(
+        "cbrt".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Number(args.get("num").unwrap().as_number().cbrt()))
+        }
+    ))
+}
+
+fn get_log() -> Value {
+    Value::Function(
+        "log".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Number(args.get("num").unwrap().as_number().ln()))
+        }
+    ))
+}
+
+fn get_log10() -> Value {
+    Value::Function(
+        "log10".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Number(args.get("num").unwrap().as_number().log10()))
+        }
+    ))
+}
+
+fn get_log2() -> Value {
+    Value::Function(
+        "log2".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Number(args.get("num").unwrap().as_number().log2()))
+        }
+    ))
+}
+
+fn get_exp() -> Value {
+    Value::Function(
+        "exp".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Number(args.get("num").unwrap().as_number().exp()))
+        }
+    ))
+}
+
+// -1, 0 or 1, NaN stays NaN
+fn get_sign() -> Value {
+    Value::Function(
+        "sign".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let num = args.get("num").unwrap().as_number();
+
+            Ok(Value::Number(if num == 0.0 { 0.0 } else { num.signum() }))
+        }
+    ))
+}
+
+fn get_trunc() -> Value {
+    Value::Function(
+        "trunc".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Number(integral(args.get("num").unwrap().as_number().trunc())))
+        }
+    ))
+}
+
+// [quotient, remainder] of floored division, the remainder takes the sign of the divisor
+fn get_divmod() -> Value {
+    Value::Function(
+        "divmod".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string()), FunctionArgument::Required("divisor".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let num = args.get("num").unwrap().as_number();
+            let divisor = args.get("divisor").unwrap().as_number();
+
+            let quotient = integral((num / divisor).floor());
+            let remainder = num - divisor * quotient;
+
+            Ok(Value::Array(vec![Box::new(Value::Number(quotient)), Box::new(Value::Number(remainder))]))
+        }
+    ))
+}
+
+// floored modulo, unlike `%` the result takes the sign of the divisor: mod(-7, 3) is 2
+fn get_mod() -> Value {
+    Value::Function(
+        "mod".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("num".to_string()), FunctionArgument::Required("divisor".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let num = args.get("num").unwrap().as_number();
+            let divisor = args.get("divisor").unwrap().as_number();
+
+            let remainder = num % divisor;
+            if remainder != 0.0 && (remainder < 0.0) != (divisor < 0.0) {
+                return Ok(Value::Number(remainder + divisor))
+            }
+
+            Ok(Value::Number(integral(remainder)))
         }
     ))
-}
\ No newline at end of file
+}
+
+// 0 below edge0, 1 above edge1 and a smooth curve between them.
+// with both edges at the same place it's a step at that edge
+fn get_smoothstep() -> Value {
+    Value::Function(
+        "smoothstep".to_owned(),
+        FunctionArguments::new(Vec::from([
+            FunctionArgument::Required("edge0".to_string()),
+            FunctionArgument::Required("edge1".to_string()),
+            FunctionArgument::Required("x".to_string())
+        ])),
+        FuncImpl::Builtin(|args| {
+            let edge0 = args.get("edge0").unwrap().as_number();
+            let edge1 = args.get("edge1").unwrap().as_number();
+            let x = args.get("x").unwrap().as_number();
+
+            if edge0 == edge1 {
+                return Ok(Value::Number(if x < edge0 { 0.0 } else { 1.0 }))
+            }
+
+            let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+
+            Ok(Value::Number(t * t * (3.0 - 2.0 * t)))
+        }
+    ))
+}
+
+// easings take the progress of an animation, clamped to 0..1
+fn get_ease_in_quad() -> Value {
+    Value::Function(
+        "easeInQuad".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("t".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let t = args.get("t").unwrap().as_number().clamp(0.0, 1.0);
+
+            Ok(Value::Number(t * t))
+        }
+    ))
+}
+
+fn get_ease_out_quad() -> Value {
+    Value::Function(
+        "easeOutQuad".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("t".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let t = args.get("t").unwrap().as_number().clamp(0.0, 1.0);
+
+            Ok(Value::Number(t * (2.0 - t)))
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{eval, interpreter::types::Value};
+
+    // tests that seed the shared RNG take turns
+    static SEEDING: Mutex<()> = Mutex::new(());
+
+    fn run(code: &str) -> Value {
+        eval(&format!("import * as math from 'math'\nlet result = {}", code)).unwrap().get("result".to_string())
+    }
+
+    #[test]
+    fn rounding_gives_integers() {
+        assert_eq!(run("math.floor(2.9)"), Value::Integer(2));
+        assert_eq!(run("math.ceil(-0.5)"), Value::Integer(0));
+        assert_eq!(run("math.round(2.5)"), Value::Integer(3));
+        assert_eq!(run("typeof math.floor(1.5)"), Value::String("number".to_string()));
+        // too big for an integer, it stays a float
+        assert_eq!(run("math.floor(2 ** 70)"), Value::Number(2f64.powi(70)));
+    }
+
+    #[test]
+    fn random_int_stays_in_range() {
+        let _seeding = SEEDING.lock().unwrap();
+        let scope = eval("
+            import * as math from 'math'
+            math.seed(7)
+            let rolls = for (i in 0..200) { math.randomInt(1, 4) }
+        ").unwrap();
+
+        match scope.get("rolls".to_string()) {
+            Value::Array(rolls) => assert!(rolls.iter().all(|roll| matches!(**roll, Value::Integer(1..=3)))),
+            value => panic!("expected an array, got {}", value)
+        }
+    }
+
+    #[test]
+    fn random_int_rejects_empty_ranges() {
+        assert!(eval("import * as math from 'math'\nmath.randomInt(9, 1)").is_err());
+        assert!(eval("import * as math from 'math'\nmath.randomInt(3, 3)").is_err());
+    }
+
+    #[test]
+    fn floor_indexes_an_array() {
+        assert_eq!(run("['a', 'b', 'c'][math.floor(2.9)]"), Value::String("c".to_string()));
+        assert_eq!(run("['a', 'b', 'c'][math.floor(10 / 4)]"), Value::String("c".to_string()));
+    }
+
+    #[test]
+    fn mod_takes_the_sign_of_the_divisor() {
+        assert_eq!(run("math.mod(-7, 3) == 2"), Value::Boolean(true));
+        assert_eq!(run("math.mod(7, -3) == -2"), Value::Boolean(true));
+        assert_eq!(run("math.mod(7, 3) == 1"), Value::Boolean(true));
+        // unlike %
+        assert_eq!(run("-7 % 3 == -1"), Value::Boolean(true));
+    }
+
+    #[test]
+    fn divmod_results_destructure() {
+        let scope = eval("
+            import * as math from 'math'
+            let [q, r] = math.divmod(17, 5)
+            let [fq, fr] = math.divmod(-7, 2)
+        ").unwrap();
+        let num = |name: &str| scope.get(name.to_string()).as_number();
+
+        assert_eq!((num("q"), num("r")), (3.0, 2.0));
+        // floored, the remainder takes the sign of the divisor
+        assert_eq!((num("fq"), num("fr")), (-4.0, 1.0));
+    }
+
+    #[test]
+    fn sqrt_and_sign() {
+        assert_eq!(run("math.sqrt(9)").as_number(), 3.0);
+        assert!(run("math.sqrt(-1)").as_number().is_nan());
+        assert_eq!(run("math.sign(-4)").as_number(), -1.0);
+        assert_eq!(run("math.sign(0)").as_number(), 0.0);
+        assert_eq!(run("math.sign(2.5)").as_number(), 1.0);
+    }
+
+    #[test]
+    fn smoothstep_at_the_edges_and_midpoint() {
+        assert_eq!(run("math.smoothstep(0, 10, 0)").as_number(), 0.0);
+        assert_eq!(run("math.smoothstep(0, 10, 5)").as_number(), 0.5);
+        assert_eq!(run("math.smoothstep(0, 10, 10)").as_number(), 1.0);
+        // clamped outside the edges
+        assert_eq!(run("math.smoothstep(0, 10, -3)").as_number(), 0.0);
+        assert_eq!(run("math.smoothstep(0, 10, 12)").as_number(), 1.0);
+        // equal edges are a step instead of a division by zero
+        assert_eq!(run("math.smoothstep(2, 2, 1)").as_number(), 0.0);
+        assert_eq!(run("math.smoothstep(2, 2, 2)").as_number(), 1.0);
+    }
+
+    #[test]
+    fn round_to_snaps_to_the_nearest_multiple() {
+        assert_eq!(run("math.roundTo(7, 5)").as_number(), 5.0);
+        assert_eq!(run("math.roundTo(8, 5)").as_number(), 10.0);
+        assert_eq!(run("math.roundTo(7.5, 5)").as_number(), 10.0);
+        assert_eq!(run("math.roundTo(-7.5, 5)").as_number(), -10.0);
+        assert!(eval("import * as math from 'math'\nmath.roundTo(3, 0)").is_err());
+        assert!(eval("import * as math from 'math'\nmath.roundTo(3, -1)").is_err());
+    }
+
+    #[test]
+    fn seeded_sample_repeats() {
+        let _seeding = SEEDING.lock().unwrap();
+        let scope = eval("
+            import * as math from 'math'
+            math.seed(3)
+            let first = [1, 2, 3, 4, 5, 6].sample(3)
+            math.seed(3)
+            let second = [1, 2, 3, 4, 5, 6].sample(3)
+        ").unwrap();
+
+        let first = scope.get("first".to_string());
+        assert_eq!(first, scope.get("second".to_string()));
+
+        match first {
+            Value::Array(picked) => {
+                assert_eq!(picked.len(), 3);
+                // no position is picked twice
+                assert!(picked.iter().enumerate().all(|(i, a)| picked[i + 1..].iter().all(|b| a != b)));
+            },
+            value => panic!("expected an array, got {}", value)
+        }
+    }
+}
@@ -1,37 +1,117 @@
 use std::collections::BTreeMap;
 
-use crate::{interpreter::{types::Value}};
+use crate::{interpreter::{types::Value, scope::Scope}, Error, RuntimeError};
 
-use self::{io::IOModule, math::MathModule};
+use self::{fs::FSModule, io::IOModule, json::JSONModule, math::MathModule, object::ObjectModule, os::OSModule, reflect::ReflectModule, stdlib::StdModule, template::TemplateModule, time::TimeModule, units::UnitsModule};
 
+pub mod fs;
 pub mod io;
+pub mod json;
 pub mod math;
+pub mod object;
+pub mod os;
+pub mod reflect;
+pub mod stdlib;
+pub mod template;
+pub mod time;
+pub mod units;
 
 pub trait CocoModule {
     fn get() -> BTreeMap<String, Box<Value>>;
 }
 
-pub fn import_module(module: &str, objects: Option<Vec<String>>) -> Value {
-    let lib = match module {
+// picks the given names from another module's exports
+pub fn reexport(lib: BTreeMap<String, Box<Value>>, names: &[&str]) -> BTreeMap<String, Box<Value>> {
+    lib
+    .into_iter()
+    .filter(|(name, _value)| names.contains(&name.as_str()))
+    .collect()
+}
+
+// 'pkg/sub' imports the `sub` namespace nested inside `pkg`.
+// the selected `objects` are bound into `scope`, the whole namespace is returned
+pub fn import_module(module: &str, scope: &mut Scope, objects: Option<Vec<String>>) -> Result<Value, Error> {
+    let unknown = || Error::runtime(RuntimeError::UnknownModule, format!("Unknown module: {}", module));
+
+    let mut path = module.split('/');
+    let name = path.next().unwrap_or_default();
+
+    let mut lib = match name {
+        "fs" => FSModule::get(),
         "io" => IOModule::get(),
+        "json" => JSONModule::get(),
         "math" => MathModule::get(),
-        _ => {
-            // FIXME
-            panic!("Unknown module: {}", module);
-        }
+        "object" => ObjectModule::get(),
+        "os" => OSModule::get(),
+        "reflect" => ReflectModule::get(),
+        "std" => StdModule::get(),
+        "template" => TemplateModule::get(),
+        "time" => TimeModule::get(),
+        "units" => UnitsModule::get(),
+        _ => return Err(unknown())
     };
 
-    if let Some(objects_some) = objects {
-        return Value::Object(
-            lib
-            .into_iter()
-            .filter(|val| objects_some.contains(&val.0))
-            .collect()
-        )
+    for namespace in path {
+        lib = match lib.remove(namespace).map(|value| *value) {
+            Some(Value::Object(sub)) => sub,
+            _ => return Err(unknown())
+        };
+    }
+
+    for object in objects.unwrap_or_default() {
+        match lib.get(&object) {
+            Some(value) => scope.set(object, *value.to_owned()),
+            None => return Err(Error::runtime(RuntimeError::NotExported, format!("{} is not exported by {}", object, module)))
+        };
     }
-    
 
-    Value::Object(lib)
+    Ok(Value::Object(lib))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, interpreter::types::Value, ErrorKind, RuntimeError};
+
+    #[test]
+    fn imported_names_are_callable() {
+        let scope = eval("
+            import { floor } from 'math'
+            let result = floor(3.7)
+        ").unwrap();
+
+        assert_eq!(scope.get("result".to_string()), Value::Integer(3));
 
-    
-}
\ No newline at end of file
+        let missing = eval("import { nothing } from 'math'").err().unwrap();
+        assert_eq!(missing.kind(), ErrorKind::Runtime(RuntimeError::NotExported));
+    }
+
+    #[test]
+    fn namespace_imports_bind_the_whole_module() {
+        let scope = eval("
+            import * as m from 'math'
+            let floored = m.floor(2.5)
+            let pi = m.PI
+        ").unwrap();
+
+        assert_eq!(scope.get("floored".to_string()), Value::Integer(2));
+        assert_eq!(scope.get("pi".to_string()), Value::Number(std::f64::consts::PI));
+    }
+
+    #[test]
+    fn nested_namespaces_are_reachable() {
+        let scope = eval("
+            import * as std from 'std'
+            import { sqrt } from 'std/math'
+            let nested = std.math.floor(1.5)
+            let reexported = std.max(1, 4)
+            let selected = sqrt(9)
+        ").unwrap();
+
+        assert_eq!(scope.get("nested".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("reexported".to_string()).as_number(), 4.0);
+        assert_eq!(scope.get("selected".to_string()).as_number(), 3.0);
+
+        let unknown = eval("import * as nope from 'std/nope'").err().unwrap();
+        assert_eq!(unknown.kind(), ErrorKind::Runtime(RuntimeError::UnknownModule));
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+
+use crate::interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}};
+
+use super::CocoModule;
+
+pub struct ObjectModule {}
+
+impl CocoModule for ObjectModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([ 
+            ("zipObject".to_string(), Box::new(get_zip_object())),
+            ("invert".to_string(), Box::new(get_invert())),
+            ("getPath".to_string(), Box::new(get_get_path())),
+            ("setPath".to_string(), Box::new(get_set_path())),
+            ("pick".to_string(), Box::new(get_pick())),
+            ("omit".to_string(), Box::new(get_omit())),
+            ("deepEqual".to_string(), Box::new(get_deep_equal()))
+        ])
+    }
+}
+
+pub fn get_zip_object() -> Value {
+    Value::Function(
+        "zipObject".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("keys".to_string()), FunctionArgument::Required("values".to_string())])),
+        FuncImpl::Builtin(|args| {
+            match (args.get("keys").unwrap(), args.get("values").unwrap()) {
+                (Value::Array(keys), Value::Array(values)) => Ok(Value::Object(
+                    keys
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(key, value)| (key.as_string(), value.to_owned()))
+                    .collect()
+                )),
+                _ => Ok(Value::Null)
+            }
+        }
+    ))
+}
+
+// values become keys as strings, the last key wins for duplicate values
+pub fn get_invert() -> Value {
+    Value::Function(
+        "invert".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("obj".to_string())])),
+        FuncImpl::Builtin(|args| {
+            match args.get("obj").unwrap() {
+                Value::Object(map) => Ok(Value::Object(
+                    map
+                    .iter()
+                    .map(|(key, value)| (value.as_string(), Box::new(Value::String(key.to_owned()))))
+                    .collect()
+                )),
+                _ => Ok(Value::Null)
+            }
+        }
+    ))
+}
+
+// a copy holding only the listed keys, keys the object doesn't have are skipped
+pub fn get_pick() -> Value {
+    Value::Function(
+        "pick".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("obj".to_string()), FunctionArgument::Required("keys".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(filter_keys(args.get("obj").unwrap(), args.get("keys").unwrap(), true))
+        }
+    ))
+}
+
+// a copy without the listed keys
+pub fn get_omit() -> Value {
+    Value::Function(
+        "omit".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("obj".to_string()), FunctionArgument::Required("keys".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(filter_keys(args.get("obj").unwrap(), args.get("keys").unwrap(), false))
+        }
+    ))
+}
+
+pub fn get_deep_equal() -> Value {
+    Value::Function(
+        "deepEqual".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("a".to_string()), FunctionArgument::Required("b".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(Value::Boolean(args.get("a").unwrap().deep_equals(args.get("b").unwrap())))
+        }
+    ))
+}
+
+fn filter_keys(obj: &Value, keys: &Value, listed: bool) -> Value {
+    match (obj, keys) {
+        (Value::Object(map), Value::Array(keys)) => {
+            let keys = keys.iter().map(|key| key.as_string()).collect::<Vec<String>>();
+
+            Value::Object(
+                map
+                .iter()
+                .filter(|(key, _)| keys.contains(key) == listed)
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect()
+            )
+        },
+        _ => Value::Null
+    }
+}
+
+// a path segment is a field name for objects and an index for arrays
+fn path_field(container: &Value, segment: &str) -> Value {
+    match container {
+        Value::Array(_values) => Value::Number(segment.parse::<f64>().unwrap_or(f64::NAN)),
+        _ => Value::String(segment.to_string())
+    }
+}
+
+pub fn get_get_path() -> Value {
+    Value::Function(
+        "getPath".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("obj".to_string()), FunctionArgument::Required("path".to_string())])),
+        FuncImpl::Builtin(|args| {
+            Ok(get_path(args.get("obj").unwrap().to_owned(), &args.get("path").unwrap().as_string()))
+        }
+    ))
+}
+
+// null when any segment of the dotted path is missing
+pub fn get_path(value: Value, path: &str) -> Value {
+    let mut current = value;
+
+    for segment in path.split('.') {
+        current = match current {
+            Value::Object(_) | Value::Instance(..) | Value::Array(_) => {
+                let field = path_field(&current, segment);
+                current.get_field(field).unwrap_or(Value::Null)
+            },
+            _ => return Value::Null
+        }
+    }
+
+    current
+}
+
+fn set_path(container: Value, segments: &[&str], value: Value) -> Value {
+    // missing or non-container intermediates are replaced by objects
+    let mut container = match container {
+        Value::Object(_) | Value::Instance(..) | Value::Array(_) => container,
+        _ => Value::Object(BTreeMap::new())
+    };
+
+    let field = path_field(&container, segments[0]);
+
+    let value = match segments.len() {
+        1 => value,
+        _ => {
+            let child = container.get_field(field.clone()).unwrap_or(Value::Null);
+            set_path(child, &segments[1..], value)
+        }
+    };
+
+    container.set_field(field, value).unwrap_or(container)
+}
+
+// returns a copy with the value set, the object passed in is left as it is,
+// so the result is assigned back: `config = setPath(config, 'a.b', 1)`
+pub fn get_set_path() -> Value {
+    Value::Function(
+        "setPath".to_owned(),
+        FunctionArguments::new(Vec::from([
+            FunctionArgument::Required("obj".to_string()),
+            FunctionArgument::Required("path".to_string()),
+            FunctionArgument::Required("value".to_string())
+        ])),
+        FuncImpl::Builtin(|args| {
+            let path = args.get("path").unwrap().as_string();
+            let segments = path.split('.').collect::<Vec<&str>>();
+
+            Ok(set_path(args.get("obj").unwrap().to_owned(), &segments, args.get("value").unwrap().to_owned()))
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, interpreter::types::Value};
+
+    #[test]
+    fn paths_are_read_and_written_through_missing_intermediates() {
+        let scope = eval("
+            let config = { server: { port: 80 } }
+            let port = getPath(config, 'server.port')
+            let missing = getPath(config, 'server.tls.cert')
+            let updated = setPath(config, 'server.tls.cert', 'cert.pem')
+            let cert = getPath(updated, 'server.tls.cert')
+            let kept = getPath(updated, 'server.port')
+            let untouched = getPath(config, 'server.tls')
+            let item = getPath({ list: [{ name: 'a' }] }, 'list.0.name')
+        ").unwrap();
+
+        assert_eq!(scope.get("port".to_string()), Value::Integer(80));
+        assert_eq!(scope.get("missing".to_string()), Value::Null);
+        assert_eq!(scope.get("cert".to_string()), Value::String("cert.pem".to_string()));
+        assert_eq!(scope.get("kept".to_string()), Value::Integer(80));
+        // setPath works on a copy
+        assert_eq!(scope.get("untouched".to_string()), Value::Null);
+        assert_eq!(scope.get("item".to_string()), Value::String("a".to_string()));
+    }
+
+    #[test]
+    fn zip_object_pairs_keys_with_values() {
+        let scope = eval("
+            let point = zipObject(['x', 'y'], [1, 2])
+            let x = point.x
+            let y = point.y
+            let short = zipObject(['a', 'b', 'c'], [1]).keys()
+        ").unwrap();
+
+        assert_eq!(scope.get("x".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("y".to_string()), Value::Integer(2));
+        // keys without a value are dropped
+        assert_eq!(scope.get("short".to_string()), Value::Array(vec![Box::new(Value::String("a".to_string()))]));
+    }
+
+    #[test]
+    fn invert_swaps_keys_and_values() {
+        let scope = eval("
+            let inverted = invert({ a: 'x', b: 2 })
+            let x = inverted.x
+            let two = inverted['2']
+        ").unwrap();
+
+        assert_eq!(scope.get("x".to_string()), Value::String("a".to_string()));
+        assert_eq!(scope.get("two".to_string()), Value::String("b".to_string()));
+    }
+
+    #[test]
+    fn pick_and_omit_keys() {
+        let scope = eval("
+            let picked = pick({ a: 1, b: 2, c: 3 }, ['a', 'c', 'z']).keys()
+            let omitted = omit({ a: 1, b: 2, c: 3 }, ['a']).keys()
+        ").unwrap();
+
+        let strings = |values: &[&str]| Value::Array(values.iter().map(|s| Box::new(Value::String(s.to_string()))).collect());
+
+        // missing keys are skipped
+        assert_eq!(scope.get("picked".to_string()), strings(&["a", "c"]));
+        assert_eq!(scope.get("omitted".to_string()), strings(&["b", "c"]));
+    }
+
+    #[test]
+    fn deep_equal_compares_nested_values() {
+        let scope = eval("
+            let same = deepEqual({ a: [1, { b: 2 }] }, { a: [1, { b: 2 }] })
+            let different = deepEqual({ a: [1] }, { a: [2] })
+            let numbers = deepEqual(1, 1.0)
+        ").unwrap();
+
+        assert_eq!(scope.get("same".to_string()), Value::Boolean(true));
+        assert_eq!(scope.get("different".to_string()), Value::Boolean(false));
+        assert_eq!(scope.get("numbers".to_string()), Value::Boolean(true));
+    }
+}
@@ -0,0 +1,96 @@
+use std::{collections::BTreeMap, env};
+
+use crate::{interpreter::types::{Value, FuncImpl, FunctionArguments, FunctionArgument}, Error, RuntimeError};
+
+use super::CocoModule;
+
+pub struct OSModule {}
+
+impl CocoModule for OSModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([
+            ("env".to_string(), Box::new(get_env())),
+            ("setEnv".to_string(), Box::new(get_set_env())),
+            ("platform".to_string(), Box::new(get_platform())),
+            ("cwd".to_string(), Box::new(get_cwd()))
+        ])
+    }
+}
+
+// null when the variable isn't set (or isn't valid unicode)
+fn get_env() -> Value {
+    Value::Function(
+        "env".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("name".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let name = args.get("name").unwrap().as_string();
+
+            Ok(env::var(name).map(Value::String).unwrap_or(Value::Null))
+        }
+    ))
+}
+
+// the variable is visible to this script and the programs it starts
+fn get_set_env() -> Value {
+    Value::Function(
+        "setEnv".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("name".to_string()), FunctionArgument::Required("value".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let name = args.get("name").unwrap().as_string();
+            let value = args.get("value").unwrap().as_string();
+
+            if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+                return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Invalid environment variable: {}", name)))
+            }
+
+            env::set_var(name, value);
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+// "linux", "macos", "windows" or the name rust uses for other systems
+fn get_platform() -> Value {
+    Value::Function(
+        "platform".to_owned(),
+        FunctionArguments::new(Vec::new()),
+        FuncImpl::Builtin(|_args| {
+            Ok(Value::String(env::consts::OS.to_string()))
+        }
+    ))
+}
+
+// null when the working directory is gone
+fn get_cwd() -> Value {
+    Value::Function(
+        "cwd".to_owned(),
+        FunctionArguments::new(Vec::new()),
+        FuncImpl::Builtin(|_args| {
+            Ok(env::current_dir().map(|path| Value::String(path.to_string_lossy().to_string())).unwrap_or(Value::Null))
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::{eval, interpreter::types::Value};
+
+    #[test]
+    fn env_reads_variables() {
+        let scope = eval("
+            import * as os from 'os'
+            let path = os.env('PATH')
+            let missing = os.env('COCO_TEST_UNSET_VARIABLE')
+            os.setEnv('COCO_TEST_ENV', 'set by a test')
+            let set = os.env('COCO_TEST_ENV')
+        ").unwrap();
+
+        assert_eq!(scope.get("path".to_string()), Value::String(env::var("PATH").unwrap()));
+        assert_eq!(scope.get("missing".to_string()), Value::Null);
+        assert_eq!(scope.get("set".to_string()), Value::String("set by a test".to_string()));
+        assert!(eval("import * as os from 'os'\nos.setEnv('A=B', 'c')").is_err());
+    }
+}
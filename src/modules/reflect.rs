@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use crate::interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}};
+
+use super::CocoModule;
+
+pub struct ReflectModule {}
+
+impl CocoModule for ReflectModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([ 
+            ("classOf".to_string(), Box::new(get_class_of()))
+        ])
+    }
+}
+
+// the class name for classes and their instances, the type name for everything else
+pub fn get_class_of() -> Value {
+    Value::Function(
+        "classOf".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("value".to_string())])),
+        FuncImpl::Builtin(|args| {
+            match args.get("value").unwrap() {
+                Value::Class(name, ..) | Value::Instance(name, _) => Ok(Value::String(name.to_owned())),
+                value => Ok(Value::String(value.type_name().to_string()))
+            }
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, interpreter::types::Value};
+
+    #[test]
+    fn class_of_names_classes_and_types() {
+        let scope = eval("
+            class Point {
+                constructor(x) {
+                    this.x = x
+                }
+            }
+            let named = classOf(Point)
+            let instance = classOf(new Point(1))
+            let object = classOf({ x: 1 })
+            let number = classOf(1)
+        ").unwrap();
+
+        assert_eq!(scope.get("named".to_string()), Value::String("Point".to_string()));
+        assert_eq!(scope.get("instance".to_string()), Value::String("Point".to_string()));
+        assert_eq!(scope.get("object".to_string()), Value::String("object".to_string()));
+        assert_eq!(scope.get("number".to_string()), Value::String("number".to_string()));
+    }
+}
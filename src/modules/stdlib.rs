@@ -0,0 +1,31 @@
+use std::collections::BTreeMap;
+
+use crate::interpreter::types::Value;
+
+use super::{CocoModule, reexport, fs::FSModule, io::IOModule, json::JSONModule, math::MathModule, object::ObjectModule, os::OSModule, reflect::ReflectModule, template::TemplateModule, time::TimeModule, units::UnitsModule};
+
+pub struct StdModule {}
+
+impl CocoModule for StdModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        // the other modules are nested namespaces: std.math.sqrt
+        let mut lib = BTreeMap::from([
+            ("fs".to_string(), Box::new(Value::Object(FSModule::get()))),
+            ("io".to_string(), Box::new(Value::Object(IOModule::get()))),
+            ("json".to_string(), Box::new(Value::Object(JSONModule::get()))),
+            ("math".to_string(), Box::new(Value::Object(MathModule::get()))),
+            ("object".to_string(), Box::new(Value::Object(ObjectModule::get()))),
+            ("os".to_string(), Box::new(Value::Object(OSModule::get()))),
+            ("reflect".to_string(), Box::new(Value::Object(ReflectModule::get()))),
+            ("template".to_string(), Box::new(Value::Object(TemplateModule::get()))),
+            ("time".to_string(), Box::new(Value::Object(TimeModule::get()))),
+            ("units".to_string(), Box::new(Value::Object(UnitsModule::get())))
+        ]);
+
+        // the most used names are also available at the top level
+        lib.extend(reexport(IOModule::get(), &["format"]));
+        lib.extend(reexport(MathModule::get(), &["PI", "max", "min"]));
+
+        lib
+    }
+}
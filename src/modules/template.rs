@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use crate::{interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}}, Error, RuntimeError};
+
+use super::{CocoModule, object::get_path};
+
+pub struct TemplateModule {}
+
+impl CocoModule for TemplateModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([ 
+            ("render".to_string(), Box::new(get_render()))
+        ])
+    }
+}
+
+// render('{{#each items}}{{name}} {{/each}}', { items: [...] })
+//
+// {{path}}                         value at a dotted path
+// {{#if path}} ... {{else}} ... {{/if}}
+// {{#each path}} ... {{/each}}     repeats for every element, `this` and `@index`
+//                                  refer to the element and its position
+fn get_render() -> Value {
+    Value::Function(
+        "render".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("template".to_string()), FunctionArgument::Required("data".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let template = args.get("template").unwrap().as_string();
+            let parts = TemplateParser::new(&template).parse()?;
+
+            let mut output = String::new();
+            render(&parts, &mut vec![args.get("data").unwrap().to_owned()], &mut output);
+
+            Ok(Value::String(output))
+        }
+    ))
+}
+
+#[derive(Debug)]
+enum Part {
+    Text(String),
+    Var(String),
+    If(String, Vec<Part>, Vec<Part>),
+    Each(String, Vec<Part>)
+}
+
+// names are looked up from the innermost `each` element outwards
+fn lookup(path: &str, stack: &[Value]) -> Value {
+    let (head, rest) = match path.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (path, None)
+    };
+
+    let value = match head {
+        "this" => stack.last().cloned().unwrap_or(Value::Null),
+        _ => stack
+            .iter()
+            .rev()
+            .find_map(|scope| match scope {
+                Value::Object(map) | Value::Instance(_, map) => map.get(head).map(|value| *value.to_owned()),
+                _ => None
+            })
+            .unwrap_or(Value::Null)
+    };
+
+    match rest {
+        Some(rest) => get_path(value, rest),
+        None => value
+    }
+}
+
+fn render(parts: &[Part], stack: &mut Vec<Value>, output: &mut String) {
+    for part in parts {
+        match part {
+            Part::Text(text) => output.push_str(text),
+            Part::Var(path) => output.push_str(&lookup(path, stack).as_string()),
+            Part::If(path, then, otherwise) => {
+                match lookup(path, stack).as_bool() {
+                    true => render(then, stack, output),
+                    false => render(otherwise, stack, output)
+                }
+            },
+            Part::Each(path, body) => {
+                if let Value::Array(items) = lookup(path, stack) {
+                    for (i, item) in items.into_iter().enumerate() {
+                        stack.push(Value::Object(BTreeMap::from([("@index".to_string(), Box::new(Value::Integer(i as i64)))])));
+                        stack.push(*item);
+                        render(body, stack, output);
+                        stack.truncate(stack.len() - 2);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a closing tag and its position
+type Closing = Option<(String, usize)>;
+
+struct TemplateParser {
+    template: String,
+    pos: usize
+}
+
+impl TemplateParser {
+    fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+            pos: 0
+        }
+    }
+
+    fn parse(&mut self) -> Result<Vec<Part>, Error> {
+        let (parts, closing) = self.block()?;
+
+        match closing {
+            Some((tag, pos)) => Err(self.error(format!("Unexpected {{{{{}}}}}", tag), pos)),
+            None => Ok(parts)
+        }
+    }
+
+    // the line and column of a byte offset in the template go in the message,
+    // the error itself points at the render call
+    fn error(&self, msg: String, pos: usize) -> Error {
+        let before = &self.template[..pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+
+        Error::runtime(RuntimeError::Template, format!("Template error at {}:{}: {}", line, column, msg))
+    }
+
+    // parses until the end of the template or a closing tag (/if, /each, else),
+    // which is returned with its position
+    fn block(&mut self) -> Result<(Vec<Part>, Closing), Error> {
+        let mut parts = vec![];
+
+        while self.pos < self.template.len() {
+            let rest = &self.template[self.pos..];
+
+            let start = match rest.find("{{") {
+                Some(start) => start,
+                None => {
+                    parts.push(Part::Text(rest.to_string()));
+                    self.pos = self.template.len();
+                    break
+                }
+            };
+
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+
+            let tag_pos = self.pos + start;
+            let end = match self.template[tag_pos..].find("}}") {
+                Some(end) => tag_pos + end,
+                None => return Err(self.error("Unclosed {{".to_string(), tag_pos))
+            };
+
+            let tag = self.template[tag_pos + 2..end].trim().to_string();
+            self.pos = end + 2;
+
+            if let Some(directive) = tag.strip_prefix('#') {
+                let (name, path) = directive.split_once(' ').unwrap_or((directive, ""));
+                let path = path.trim().to_string();
+
+                if path.is_empty() {
+                    return Err(self.error(format!("Missing value for #{}", name), tag_pos))
+                }
+
+                match name {
+                    "if" => {
+                        let (then, closing) = self.block()?;
+                        let otherwise = match closing {
+                            Some((tag, _)) if tag == "/if" => vec![],
+                            Some((tag, _)) if tag == "else" => self.closed_by("/if", tag_pos)?,
+                            Some((tag, pos)) => return Err(self.error(format!("Unexpected {{{{{}}}}}", tag), pos)),
+                            None => return Err(self.error("Missing {{/if}}".to_string(), tag_pos))
+                        };
+
+                        parts.push(Part::If(path, then, otherwise));
+                    },
+                    "each" => {
+                        let body = self.closed_by("/each", tag_pos)?;
+
+                        parts.push(Part::Each(path, body));
+                    },
+                    _ => return Err(self.error(format!("Unknown directive #{}", name), tag_pos))
+                }
+
+                continue
+            }
+
+            if tag.starts_with('/') || tag == "else" {
+                return Ok((parts, Some((tag, tag_pos))))
+            }
+
+            parts.push(Part::Var(tag));
+        }
+
+        Ok((parts, None))
+    }
+
+    fn closed_by(&mut self, expected: &str, open_pos: usize) -> Result<Vec<Part>, Error> {
+        let (parts, closing) = self.block()?;
+
+        match closing {
+            Some((tag, _)) if tag == expected => Ok(parts),
+            Some((tag, pos)) => Err(self.error(format!("Unexpected {{{{{}}}}}", tag), pos)),
+            None => Err(self.error(format!("Missing {{{{{}}}}}", expected), open_pos))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, interpreter::types::Value};
+
+    #[test]
+    fn renders_a_list() {
+        let scope = eval("
+            import * as template from 'template'
+            let text = template.render('{{#each items}}{{@index}}. {{name}}; {{/each}}', {
+                items: [{ name: 'milk' }, { name: 'eggs' }]
+            })
+        ").unwrap();
+
+        assert_eq!(scope.get("text".to_string()), Value::String("0. milk; 1. eggs; ".to_string()));
+    }
+
+    #[test]
+    fn errors_name_the_template_position() {
+        let error = eval("
+            import * as template from 'template'
+            template.render('ok\n  {{/if}}', {})
+        ").err().unwrap();
+
+        assert!(error.msg.contains("at 2:3"), "{}", error.msg);
+        // the script position is the render call's
+        assert_eq!(error.pos[0], 3);
+    }
+}
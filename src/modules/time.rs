@@ -0,0 +1,210 @@
+use std::{collections::BTreeMap, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use crate::{Error, RuntimeError, interpreter::{types::{Value, FuncImpl, FunctionArguments, FunctionArgument}, call_function}, parser::Node};
+
+use super::CocoModule;
+
+pub struct TimeModule {}
+
+impl CocoModule for TimeModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([ 
+            ("now".to_string(), Box::new(get_now())),
+            ("sleep".to_string(), Box::new(get_sleep())),
+            ("format".to_string(), Box::new(get_format())),
+            ("bench".to_string(), Box::new(get_bench()))
+        ])
+    }
+}
+
+// milliseconds since the unix epoch
+fn get_now() -> Value {
+    Value::Function(
+        "now".to_owned(),
+        FunctionArguments::new(Vec::new()),
+        FuncImpl::Builtin(|_args| {
+            let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+            Ok(Value::Number(elapsed.as_secs_f64() * 1000.0))
+        }
+    ))
+}
+
+// blocks for `ms` milliseconds, fractions included. negative waits are no wait,
+// NaN, infinite and unrepresentably long ones are errors
+fn get_sleep() -> Value {
+    Value::Function(
+        "sleep".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("ms".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let ms = args.get("ms").unwrap().as_number();
+            let wait = if ms < 0.0 { 0.0 } else { ms };
+
+            match Duration::try_from_secs_f64(wait / 1000.0) {
+                Ok(duration) => thread::sleep(duration),
+                Err(_) => return Err(Error::runtime(RuntimeError::InvalidArgument, format!("Invalid sleep duration: {}", Value::Number(ms).as_string())))
+            }
+
+            Ok(Value::Null)
+        }
+    ))
+}
+
+// the utc time `ms` after the epoch, written with the pattern:
+// YYYY year, MM month, DD day, HH hours, mm minutes, ss seconds, SSS milliseconds.
+// anything else is copied, the default pattern is 'YYYY-MM-DD HH:mm:ss'
+fn get_format() -> Value {
+    Value::Function(
+        "format".to_owned(),
+        FunctionArguments::new(Vec::from([
+            FunctionArgument::Required("ms".to_string()),
+            FunctionArgument::NotRequired("pattern".to_string(), Node::String("YYYY-MM-DD HH:mm:ss".to_string()))
+        ])),
+        FuncImpl::Builtin(|args| {
+            let ms = args.get("ms").unwrap().as_number();
+            let pattern = args.get("pattern").unwrap().as_string();
+
+            if !ms.is_finite() {
+                return Ok(Value::Null)
+            }
+
+            Ok(Value::String(format_time(ms.floor() as i64, &pattern)))
+        }
+    ))
+}
+
+fn format_time(ms: i64, pattern: &str) -> String {
+    let days = ms.div_euclid(86_400_000);
+    let ms_of_day = ms.rem_euclid(86_400_000);
+    let (year, month, day) = civil_from_days(days);
+
+    let fields = [
+        ("YYYY", format!("{:04}", year)),
+        ("SSS", format!("{:03}", ms_of_day % 1000)),
+        ("MM", format!("{:02}", month)),
+        ("DD", format!("{:02}", day)),
+        ("HH", format!("{:02}", ms_of_day / 3_600_000)),
+        ("mm", format!("{:02}", ms_of_day / 60_000 % 60)),
+        ("ss", format!("{:02}", ms_of_day / 1000 % 60))
+    ];
+
+    let mut result = String::new();
+    let mut rest = pattern;
+
+    'outer: while !rest.is_empty() {
+        for (token, value) in fields.iter() {
+            if let Some(after) = rest.strip_prefix(token) {
+                result.push_str(value);
+                rest = after;
+                continue 'outer
+            }
+        }
+
+        let mut chars = rest.chars();
+        result.push(chars.next().unwrap());
+        rest = chars.as_str();
+    }
+
+    result
+}
+
+// the gregorian (year, month, day) of a count of days since 1970-01-01
+// (Howard Hinnant's civil_from_days)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+// runs `fn` `iterations` times after `warmup` untimed runs,
+// returns the average time of a run in milliseconds
+pub fn get_bench() -> Value {
+    Value::Function(
+        "bench".to_owned(),
+        FunctionArguments::new(Vec::from([
+            FunctionArgument::Required("fn".to_string()),
+            FunctionArgument::NotRequired("iterations".to_string(), Node::Number(1.0)),
+            FunctionArgument::NotRequired("warmup".to_string(), Node::Number(0.0))
+        ])),
+        FuncImpl::Callback(|args, scope| {
+            let function = args.get("fn").unwrap().to_owned();
+            let iterations = args.get("iterations").unwrap().as_number().max(1.0) as usize;
+            let warmup = args.get("warmup").unwrap().as_number().max(0.0) as usize;
+
+            for _ in 0..warmup {
+                call_function(function.clone(), vec![], scope)?;
+            }
+
+            let start = Instant::now();
+
+            for _ in 0..iterations {
+                call_function(function.clone(), vec![], scope)?;
+            }
+
+            Ok(Value::Number(start.elapsed().as_secs_f64() * 1000.0 / iterations as f64))
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, interpreter::types::Value};
+
+    #[test]
+    fn now_does_not_go_backwards() {
+        let scope = eval("
+            import * as time from 'time'
+            let first = time.now()
+            time.sleep(1)
+            let second = time.now()
+        ").unwrap();
+
+        let first = scope.get("first".to_string()).as_number();
+        let second = scope.get("second".to_string()).as_number();
+
+        assert!(first > 0.0);
+        assert!(second >= first, "{} then {}", first, second);
+    }
+
+    #[test]
+    fn sleep_rejects_durations_it_cannot_wait() {
+        assert!(eval("import * as time from 'time'\ntime.sleep(-5)\ntime.sleep(0.5)").is_ok());
+        assert!(eval("import * as time from 'time'\ntime.sleep(1 / 0)").is_err());
+        assert!(eval("import * as time from 'time'\ntime.sleep(0 / 0)").is_err());
+    }
+
+    #[test]
+    fn format_writes_utc_fields() {
+        let scope = eval("
+            import * as time from 'time'
+            let date = time.format(86400000 + 3723004, 'YYYY-MM-DD HH:mm:ss.SSS')
+        ").unwrap();
+
+        assert_eq!(scope.get("date".to_string()), Value::String("1970-01-02 01:02:03.004".to_string()));
+    }
+
+    #[test]
+    fn bench_returns_a_non_negative_average() {
+        let scope = eval("
+            let calls = 0
+            fun trivial() {
+                global calls = calls + 1
+            }
+            let average = bench(trivial, 5, 2)
+        ").unwrap();
+
+        let average = scope.get("average".to_string()).as_number();
+
+        assert!(average >= 0.0, "{}", average);
+        // warmup runs are called but not timed
+        assert_eq!(scope.get("calls".to_string()), Value::Integer(7));
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use crate::{interpreter::types::{Value, FuncImpl, FunctionArguments, FunctionArgument}, Error, RuntimeError};
+
+use super::CocoModule;
+
+// how many base units one unit is: durations are counted in milliseconds, sizes in bytes
+const UNITS: [(&str, f64); 10] = [
+    ("ms", 1.0),
+    ("s", 1000.0),
+    ("min", 60_000.0),
+    ("h", 3_600_000.0),
+    ("d", 86_400_000.0),
+    ("b", 1.0),
+    ("kb", 1024.0),
+    ("mb", 1_048_576.0),
+    ("gb", 1_073_741_824.0),
+    ("tb", 1_099_511_627_776.0)
+];
+
+pub struct UnitsModule {}
+
+impl CocoModule for UnitsModule {
+    fn get() -> BTreeMap<String, Box<Value>> {
+        BTreeMap::from([ 
+            ("convert".to_string(), Box::new(get_convert()))
+        ])
+    }
+}
+
+// number literals with a unit suffix are converted with this: 2s is convert(2, 's'), 2000
+pub fn convert(value: f64, unit: &str) -> Result<Value, Error> {
+    match UNITS.iter().find(|(name, _)| *name == unit) {
+        Some((_, factor)) => Ok(Value::Number(value * factor)),
+        None => Err(Error::runtime(RuntimeError::UnknownUnit, format!("Unknown unit: {}", unit)))
+    }
+}
+
+fn get_convert() -> Value {
+    Value::Function(
+        "convert".to_owned(),
+        FunctionArguments::new(Vec::from([FunctionArgument::Required("value".to_string()), FunctionArgument::Required("unit".to_string())])),
+        FuncImpl::Builtin(|args| {
+            let value = args.get("value").unwrap().as_number();
+            let unit = args.get("unit").unwrap().as_string();
+
+            convert(value, &unit)
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{eval, interpreter::types::Value, ErrorKind, RuntimeError};
+
+    #[test]
+    fn suffixes_convert_without_an_import() {
+        let scope = eval("
+            let units = 'shadowed'
+            let wait = 2s
+            let size = 3kb
+        ").unwrap();
+
+        assert_eq!(scope.get("wait".to_string()), Value::Number(2000.0));
+        assert_eq!(scope.get("size".to_string()), Value::Number(3072.0));
+    }
+
+    #[test]
+    fn unknown_suffixes_are_errors() {
+        assert_eq!(eval("let x = 5parsecs").unwrap_err().kind(), ErrorKind::Runtime(RuntimeError::UnknownUnit));
+    }
+}
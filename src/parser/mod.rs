@@ -1,6 +1,6 @@
 use std::collections::{ BTreeMap };
 
-use crate::{lexer::{ Token, TokenType }, interpreter::types::{FunctionArguments, FunctionArgument}, Error, Resolver};
+use crate::{lexer::{ Token, TokenType }, interpreter::types::{FunctionArguments, FunctionArgument, format_number}, Error, ParseError, Resolver};
 use phf::phf_map;
 
 const ASSIGNOP: phf::Map<&str, AssignmentOp> = phf_map! {
@@ -22,8 +22,8 @@ pub enum AssignmentOp {
     DIVEQ,   // a /= 1
     REMEQ,   // a %= 1
     EXPEQ,   // a **= 1
-    // MINUSMINUS
-    // PLUSPLUS
+    PLUSPLUS,   // a++
+    MINUSMINUS, // a--
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -50,8 +50,9 @@ pub enum BinaryOp {
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum UnaryOp {
-    MINUS, // -a
-    NOT    // !a
+    MINUS,  // -a
+    NOT,    // !a
+    TYPEOF  // typeof a
 }
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
@@ -66,31 +67,47 @@ pub enum Node {
     ImportObjects(String, Vec<String>),
 
     Assign(Box<Node>, Box<Node>),
+    Global(String, Box<Node>),
     AssignOp(AssignmentOp, Box<Node>, Box<Node>),
 
     String(String),
+    Integer(i64),
     Number(f64),
     Bool(bool),
     Array(Vec<Box<Node>>),
     Object(BTreeMap<String, Box<Node>>),
-    Class(String, Option<Box<Node>>, BTreeMap<String, Node>),
+    Class(String, Option<Box<Node>>, BTreeMap<String, Node>, Option<Box<Node>>),
     Null,
 
     // ArrayFun()
 
     Var(String),
+    // the `...rest` of a destructuring pattern
+    Rest(String),
+    This,
+    Super,
     FieldAccess(Box<Node>, Vec<Box<Node>>),
 
     Range(Box<Node>, Box<Node>, bool),
+    // a number with a unit suffix, the position is the suffix's
+    Unit(Box<Node>, String, Vec<usize>),
 
     BlockStatement(Vec<Box<Node>>),
     IfElseStatement(Box<Node>, Box<Node>, Box<Option<Node>>),
-    WhileStatement(Box<Node>, Box<Node>),
-    ForStatement(String, Box<Node>, Box<Node>),
+    // the else block runs when the loop ends without a break
+    WhileStatement(Box<Node>, Box<Node>, Box<Option<Node>>, bool),
+    DoWhileStatement(Box<Node>, Box<Node>),
+    // positions are [line, column] of the `for` and of the call's `(`
+    ForStatement(String, Box<Node>, Box<Node>, Box<Option<Node>>, bool, Vec<usize>),
     SwitchStatement(Box<Node>, Vec<SwitchCase>),
+    // try { ... } catch (name) { ... }
+    TryCatch(Box<Node>, String, Box<Node>),
+    Throw(Box<Node>, Vec<usize>),
     // FIXME: args
-    FunCall(Box<Node>, Vec<Box<Node>>),
+    FunCall(Box<Node>, Vec<Box<Node>>, Vec<usize>),
     Return(Box<Node>),
+    Break,
+    Continue,
     Fun(Box<Node>, FunctionArguments, Box<Node>),
     Logical(LogicalOp, Box<Node>, Box<Node>),
     Binary(BinaryOp, Box<Node>, Box<Node>),
@@ -101,7 +118,13 @@ pub enum Node {
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
-    resolver: Resolver
+    resolver: Resolver,
+    // the first token mismatch, reported by parse()
+    error: Option<Error>,
+    // how many loops the current statement is in, break and continue need one
+    loop_depth: usize,
+    // set for a loop that is the whole body of an if, the else after it is the if's
+    else_for_if: bool
 }
 
 impl Parser {
@@ -109,7 +132,10 @@ impl Parser {
         Self {
             tokens,
             pos: 0,
-            resolver: resolver.to_owned()
+            resolver: resolver.to_owned(),
+            error: None,
+            loop_depth: 0,
+            else_for_if: false
         }
     }
 
@@ -117,7 +143,13 @@ impl Parser {
         let mut root: Vec<Box<Node>> = vec![];
 
         while !self.match_token(TokenType::EOF) {
-            root.push(Box::new(self.statement()?))
+            let statement = self.statement();
+
+            if let Some(error) = self.error.take() {
+                return Err(error)
+            }
+
+            root.push(Box::new(statement?))
         }
 
         Ok(Node::BlockStatement(root))
@@ -127,7 +159,7 @@ impl Parser {
         let mut root: Vec<Box<Node>> = vec![];
 
         self.match_token(TokenType::LBRACE);
-        while !self.match_token(TokenType::RBRACE) {
+        while self.is_open(TokenType::RBRACE) {
             root.push(Box::new(self.statement()?))
         }
 
@@ -148,14 +180,23 @@ impl Parser {
         match current.token_type {
             TokenType::LET => {
                 self.match_token(TokenType::LET);
-                let name = self.consume_token(TokenType::WORD);
+
+                // let [head, ...tail] = array, let { x, y } = object
+                let target = if self.match_token(TokenType::LBRACKET) {
+                    Node::Array(self.pattern(TokenType::RBRACKET)?.into_iter().map(|(_, name)| Box::new(name)).collect())
+                } else if self.match_token(TokenType::LBRACE) {
+                    Node::Object(self.pattern(TokenType::RBRACE)?.into_iter().map(|(key, name)| (key, Box::new(name))).collect())
+                } else {
+                    Node::Var(self.consume_token(TokenType::WORD).text)
+                };
+
                 self.consume_token(TokenType::EQUALS);
                 let value = self.expression();
 
                 Ok(
                     Node::Assign(
                         Box::new(
-                            Node::Var(name.text)
+                            target
                         ), 
                         Box::new(
                             value?
@@ -163,17 +204,20 @@ impl Parser {
                     )
                 )
             },
+            // global name = value
+            TokenType::GLOBAL => {
+                self.match_token(TokenType::GLOBAL);
+                let name = self.consume_token(TokenType::WORD);
+                self.consume_token(TokenType::EQUALS);
+                let value = self.expression()?;
+
+                Ok(Node::Global(name.text, Box::new(value)))
+            },
             TokenType::FUN => {
                 self.match_token(TokenType::FUN);
                 let name = self.consume_token(TokenType::WORD);
-                self.consume_token(TokenType::LPAR);
-                let mut args: FunctionArguments = FunctionArguments::new(vec![]);
-                while !self.match_token(TokenType::RPAR) {
-                    let arg = self.consume_token(TokenType::WORD);
-                    args.add(FunctionArgument::Required(arg.text));
-                    self.match_token(TokenType::COMMA);
-                }
-                let block = self.block();
+                let args = self.function_arguments()?;
+                let block = self.function_body();
 
                 Ok(
                     Node::Fun(
@@ -190,24 +234,24 @@ impl Parser {
             TokenType::CLASS => {
                 self.match_token(TokenType::CLASS);
                 let class_name = self.consume_token(TokenType::WORD).text;
-                // TODO extending
+
+                // class Square : Rectangle { ... }
+                let mut parent = None;
+                if self.match_token(TokenType::COLON) {
+                    parent = Some(Box::new(self.variable_expression()?));
+                }
+
                 self.match_token(TokenType::LBRACE);
                 let mut prototype: BTreeMap<String, Node> = BTreeMap::default();
                 let mut constructor = None;
-                while !self.match_token(TokenType::RBRACE) {
+                while self.is_open(TokenType::RBRACE) {
                     let class_current = self.get_token(None);
 
                     if class_current.token_type == TokenType::WORD {
                         let name = self.consume_token(TokenType::WORD).text;
                         // TODO vars
-                        self.consume_token(TokenType::LPAR);
-                        let mut args: FunctionArguments = FunctionArguments::new(vec![]);
-                        while !self.match_token(TokenType::RPAR) {
-                            let arg = self.consume_token(TokenType::WORD);
-                            args.add(FunctionArgument::Required(arg.text));
-                            self.match_token(TokenType::COMMA);
-                        }
-                        let block = self.block();
+                        let args = self.function_arguments()?;
+                        let block = self.function_body();
 
                         if name == "constructor" {
                             constructor = Some(Box::new(Node::Fun(
@@ -230,58 +274,38 @@ impl Parser {
                                 ),
                             ));
                         }
+                    } else {
+                        return Err(Error::parse(ParseError::InvalidClassBody, "Expected a method in class body".to_string(), self.resolver.resolve_where(class_current.pos)))
                     }
                 }
 
-                Ok(Node::Class(class_name, constructor, prototype))
+                Ok(Node::Class(class_name, constructor, prototype, parent))
             }
-            TokenType::IF => {
-                self.match_token(TokenType::IF);
-                self.consume_token(TokenType::LPAR);
-                let condition = self.expression();
-                self.consume_token(TokenType::RPAR);
-                let if_statement = self.statement_or_block();
-
-                let mut else_statement: Option<Node> = None;
-                if self.match_token(TokenType::ELSE) {
-                    else_statement = Some(self.statement_or_block()?);
+            TokenType::IF => self.if_statement(),
+            TokenType::FOR => self.for_statement(false),
+            TokenType::WHILE => self.while_statement(false),
+            TokenType::DO => self.do_while_statement(),
+            TokenType::BREAK | TokenType::CONTINUE => {
+                if self.loop_depth == 0 {
+                    return Err(Error::parse(ParseError::OutsideLoop, format!("{} outside of a loop", current.text), self.resolver.resolve_where(current.pos)))
                 }
 
-                Ok(
-                    Node::IfElseStatement(
-                        Box::new(condition?),
-                        Box::new(if_statement?),
-                        Box::new(else_statement)
-                    )
-                )
-            },
-            TokenType::FOR => {
-                self.match_token(TokenType::FOR);
-                self.consume_token(TokenType::LPAR);
-                let variable = self.consume_token(TokenType::WORD).text;
-                self.consume_token(TokenType::IN);
-                let iterator = self.expression()?;
-                self.consume_token(TokenType::RPAR);
-                let block = self.block()?;
-
-                Ok(
-                    Node::ForStatement(
-                        variable,
-                        Box::new(iterator),
-                        Box::new(block)
-                    )
-                )
-            },
-            TokenType::WHILE => {
-                self.match_token(TokenType::WHILE);
-                self.consume_token(TokenType::LPAR);
-                let condition = self.expression()?;
-                self.consume_token(TokenType::RPAR);
-                let block = self.block()?;
-
-                Ok(Node::WhileStatement(Box::new(condition), Box::new(block)))
+                match self.match_token(TokenType::BREAK) {
+                    true => Ok(Node::Break),
+                    false => {
+                        self.match_token(TokenType::CONTINUE);
+                        Ok(Node::Continue)
+                    }
+                }
             },
             TokenType::SWITCH => self.switch_statement(),
+            TokenType::TRY => self.try_statement(),
+            TokenType::THROW => {
+                let pos = self.resolver.resolve_where(current.pos);
+                self.match_token(TokenType::THROW);
+                let thrown = self.expression()?;
+                Ok(Node::Throw(Box::new(thrown), pos))
+            },
             TokenType::RETURN => {
                 self.match_token(TokenType::RETURN);
                 let returning = self.expression();
@@ -294,12 +318,20 @@ impl Parser {
                 // import * as name from 'name'
                 if self.get_token(None).token_type == TokenType::STAR {
                     self.match_token(TokenType::STAR);
-                    self.match_token(TokenType::AS);
+                    self.consume_token(TokenType::AS);
                     let placeholder = self.consume_token(TokenType::WORD).text;
 
-                    self.match_token(TokenType::FROM);
+                    self.consume_token(TokenType::FROM);
+
+                    let lib_name = self.consume_token(TokenType::STRING).text;
+
+                    return Ok(Node::ImportPlaceholder(lib_name, placeholder))
+                }
 
+                // import 'pkg/name' binds the namespace as `name`
+                if self.get_token(None).token_type == TokenType::STRING {
                     let lib_name = self.consume_token(TokenType::STRING).text;
+                    let placeholder = lib_name.rsplit('/').next().unwrap_or_default().to_string();
 
                     return Ok(Node::ImportPlaceholder(lib_name, placeholder))
                 }
@@ -325,17 +357,218 @@ impl Parser {
         }
     }
 
+    // the names of a destructuring pattern with the nodes binding them,
+    // a `...rest` can only come last
+    pub fn pattern(&mut self, close: TokenType) -> Result<Vec<(String, Node)>, Error> {
+        let mut names = vec![];
+        while self.is_open(close.clone()) {
+            if self.match_token(TokenType::SPREAD) {
+                let name = self.consume_token(TokenType::WORD).text;
+                names.push((name.clone(), Node::Rest(name)));
+                self.match_token(TokenType::COMMA);
+
+                if !self.match_token(close) {
+                    return Err(Error::parse(ParseError::InvalidPattern, "Rest element must be the last in a pattern".to_string(), self.resolver.resolve_where(self.get_token(None).pos)))
+                }
+                break
+            }
+
+            let name = self.consume_token(TokenType::WORD).text;
+            names.push((name.clone(), Node::Var(name)));
+            self.match_token(TokenType::COMMA);
+        }
+
+        Ok(names)
+    }
+
+    pub fn function_arguments(&mut self) -> Result<FunctionArguments, Error> {
+        self.consume_token(TokenType::LPAR);
+        let mut args: FunctionArguments = FunctionArguments::new(vec![]);
+        while self.is_open(TokenType::RPAR) {
+            // fun sum(...nums)
+            if self.match_token(TokenType::SPREAD) {
+                let arg = self.consume_token(TokenType::WORD);
+                args.add(FunctionArgument::Spread(arg.text));
+                self.match_token(TokenType::COMMA);
+
+                if !self.match_token(TokenType::RPAR) {
+                    return Err(Error::parse(ParseError::InvalidParameters, "Spread parameter must be the last parameter".to_string(), self.resolver.resolve_where(self.get_token(None).pos)))
+                }
+                break
+            }
+
+            let arg = self.consume_token(TokenType::WORD);
+
+            // fun greet(name = 'world')
+            if self.match_token(TokenType::EQUALS) {
+                args.add(FunctionArgument::NotRequired(arg.text, self.expression()?));
+            } else {
+                args.add(FunctionArgument::Required(arg.text));
+            }
+            self.match_token(TokenType::COMMA);
+        }
+
+        Ok(args)
+    }
+
+    // loops outside the function can't be left from inside it
+    pub fn function_body(&mut self) -> Result<Node, Error> {
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.function_block();
+        self.loop_depth = loop_depth;
+
+        body
+    }
+
+    fn function_block(&mut self) -> Result<Node, Error> {
+        // fun name(a, b) -> a + b
+        if self.match_token(TokenType::ARROW) {
+            if self.get_token(None).token_type == TokenType::LBRACE {
+                return self.block()
+            }
+
+            let returning = self.expression()?;
+            return Ok(Node::BlockStatement(vec![Box::new(Node::Return(Box::new(returning)))]))
+        }
+
+        self.block()
+    }
+
+    // checks if parentheses at the current position open an anonymous function: (a, b) -> ...
+    pub fn is_lambda(&self) -> bool {
+        let mut depth = 0;
+        let mut offset = 0;
+
+        loop {
+            match self.get_token(Some(offset)).token_type {
+                TokenType::LPAR => depth += 1,
+                TokenType::RPAR => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.get_token(Some(offset + 1)).token_type == TokenType::ARROW
+                    }
+                },
+                TokenType::EOF => return false,
+                _ => {}
+            }
+            offset += 1;
+        }
+    }
+
+    // the branches are parsed innermost first, so in `if (a) if (b) x else y`
+    // the else is taken by the nearest if, and `else if` chains nest. a loop
+    // doesn't take it, `if (a) while (b) { ... } else { ... }` keeps the else on the if
+    pub fn if_statement(&mut self) -> Result<Node, Error> {
+        self.match_token(TokenType::IF);
+        self.consume_token(TokenType::LPAR);
+        let condition = self.expression()?;
+        self.consume_token(TokenType::RPAR);
+        self.else_for_if = matches!(self.get_token(None).token_type, TokenType::WHILE | TokenType::FOR);
+        let if_statement = self.statement_or_block()?;
+
+        let mut else_statement: Option<Node> = None;
+        if self.match_token(TokenType::ELSE) {
+            else_statement = Some(self.statement_or_block()?);
+        }
+
+        Ok(
+            Node::IfElseStatement(
+                Box::new(condition),
+                Box::new(if_statement),
+                Box::new(else_statement)
+            )
+        )
+    }
+
+    // in expression position loops collect the value of each iteration into an array
+    pub fn for_statement(&mut self, collect: bool) -> Result<Node, Error> {
+        let takes_else = !std::mem::take(&mut self.else_for_if);
+        let pos = self.resolver.resolve_where(self.get_token(None).pos);
+        self.match_token(TokenType::FOR);
+        self.consume_token(TokenType::LPAR);
+        let variable = self.consume_token(TokenType::WORD).text;
+        self.consume_token(TokenType::IN);
+        let iterator = self.expression()?;
+        self.consume_token(TokenType::RPAR);
+        let block = self.loop_block()?;
+        let else_block = self.loop_else(takes_else)?;
+
+        Ok(
+            Node::ForStatement(
+                variable,
+                Box::new(iterator),
+                Box::new(block),
+                Box::new(else_block),
+                collect,
+                pos
+            )
+        )
+    }
+
+    pub fn while_statement(&mut self, collect: bool) -> Result<Node, Error> {
+        let takes_else = !std::mem::take(&mut self.else_for_if);
+        self.match_token(TokenType::WHILE);
+        self.consume_token(TokenType::LPAR);
+        let condition = self.expression()?;
+        self.consume_token(TokenType::RPAR);
+        let block = self.loop_block()?;
+        let else_block = self.loop_else(takes_else)?;
+
+        Ok(Node::WhileStatement(Box::new(condition), Box::new(block), Box::new(else_block), collect))
+    }
+
+    fn loop_block(&mut self) -> Result<Node, Error> {
+        self.loop_depth += 1;
+        let block = self.block();
+        self.loop_depth -= 1;
+
+        block
+    }
+
+    // while (...) { ... } else { ... }
+    fn loop_else(&mut self, takes_else: bool) -> Result<Option<Node>, Error> {
+        if takes_else && self.match_token(TokenType::ELSE) {
+            return Ok(Some(self.block()?))
+        }
+
+        Ok(None)
+    }
+
+    // do { ... } while (cond)
+    pub fn do_while_statement(&mut self) -> Result<Node, Error> {
+        self.match_token(TokenType::DO);
+        let block = self.loop_block()?;
+        self.consume_token(TokenType::WHILE);
+        self.consume_token(TokenType::LPAR);
+        let condition = self.expression()?;
+        self.consume_token(TokenType::RPAR);
+
+        Ok(Node::DoWhileStatement(Box::new(block), Box::new(condition)))
+    }
+
+    pub fn try_statement(&mut self) -> Result<Node, Error> {
+        self.match_token(TokenType::TRY);
+        let block = self.block()?;
+        self.consume_token(TokenType::CATCH);
+        self.consume_token(TokenType::LPAR);
+        let name = self.consume_token(TokenType::WORD).text;
+        self.consume_token(TokenType::RPAR);
+        let handler = self.block()?;
+
+        Ok(Node::TryCatch(Box::new(block), name, Box::new(handler)))
+    }
+
     pub fn switch_statement(&mut self) -> Result<Node, Error> {
         self.match_token(TokenType::SWITCH);
         self.consume_token(TokenType::LPAR);
-        // FIXME: variables only
-        let variable = self.variable_expression();
+        // any expression, e.g. switch (typeof x)
+        let subject = self.expression()?;
         self.consume_token(TokenType::RPAR);
 
         let mut cases: Vec<SwitchCase> = vec![]; 
 
         self.match_token(TokenType::LBRACE);
-        while !self.match_token(TokenType::RBRACE) {
+        while self.is_open(TokenType::RBRACE) {
             let current = self.get_token(None);
             match current.token_type {
                 
@@ -346,10 +579,7 @@ impl Parser {
                     }).count();
 
                     if count_default_cases == 1 {
-                        return Err(Error {
-                            msg: "Switch case can not have two or more default cases".to_string(),
-                            pos: self.resolver.resolve_where(self.get_token(None).pos)
-                        })
+                        return Err(Error::parse(ParseError::InvalidSwitch, "Switch case can not have two or more default cases".to_string(), self.resolver.resolve_where(self.get_token(None).pos)))
                     }
 
                     self.consume_token(TokenType::COLON);
@@ -369,13 +599,13 @@ impl Parser {
                     }
                     cases.push(SwitchCase::Case(value?, statement))
                 },
-                _ => {}
+                _ => return Err(Error::parse(ParseError::InvalidSwitch, "Expected case or default in switch".to_string(), self.resolver.resolve_where(current.pos)))
             }
         }
 
         Ok(
             Node::SwitchStatement(
-                Box::new(variable?), 
+                Box::new(subject), 
                 cases
             )
         )
@@ -397,6 +627,8 @@ impl Parser {
         // FIXME
         match current.token_type {
             TokenType::WORD |
+            TokenType::THIS |
+            TokenType::SUPER |
 
             TokenType::STRING |
             TokenType::NUMBER |
@@ -416,14 +648,32 @@ impl Parser {
             },
 
             TokenType::LPAR => {
+                if self.is_lambda() {
+                    let args = self.function_arguments()?;
+                    let block = self.function_body()?;
+
+                    return Ok(Node::Fun(Box::new(Node::Null), args, Box::new(block)))
+                }
+
                 self.match_token(TokenType::LPAR);
                 let expr = self.expression()?;
                 self.match_token(TokenType::RPAR);
-                Ok(expr)
+
+                // (expr).field and (expr).method()
+                let field_access = self.field_access_expression(expr)?;
+                if matches!(field_access, Node::FieldAccess(..)) && self.get_token(None).token_type == TokenType::LPAR {
+                    return self.function_chain_expression(field_access)
+                }
+
+                Ok(field_access)
             },
             
             TokenType::SWITCH => Ok(self.switch_statement()?),
 
+            TokenType::FOR => self.for_statement(true),
+
+            TokenType::WHILE => self.while_statement(true),
+
             TokenType::NEW => {
                 self.match_token(TokenType::NEW);
                 let var = self.variable_expression()?;
@@ -434,10 +684,7 @@ impl Parser {
 
             _ => {
                 //println!("{:#?}", current);
-                Err(Error {
-                    msg: "Unknown expression".to_string(),
-                    pos: self.resolver.resolve_where(self.get_token(None).pos)
-                })
+                Err(Error::parse(ParseError::UnknownExpression, "Unknown expression".to_string(), self.resolver.resolve_where(self.get_token(None).pos)))
             }
         }
     }
@@ -466,19 +713,21 @@ impl Parser {
     }
 
     pub fn function_call_expression(&mut self, variable: Node) -> Result<Node, Error> {
+        let pos = self.resolver.resolve_where(self.get_token(None).pos);
         self.consume_token(TokenType::LPAR);
         let mut args = vec![];
 
-        while !self.match_token(TokenType::RPAR) {
+        while self.is_open(TokenType::RPAR) {
             args.push(Box::new(self.expression()?));
             self.match_token(TokenType::COMMA);
         }
 
-        Ok(Node::FunCall(Box::new(variable), args))
+        Ok(Node::FunCall(Box::new(variable), args, pos))
     }
 
     pub fn var_val_expression(&mut self) -> Result<Node, Error> {
-        if self.get_token(None).token_type == TokenType::WORD {
+        let current = self.get_token(None);
+        if [TokenType::WORD, TokenType::THIS, TokenType::SUPER].contains(&current.token_type) {
             return self.variable_expression()
         }
 
@@ -525,13 +774,18 @@ impl Parser {
                 self.match_token(current.token_type);
                 let name = current.text;
                 Ok(Node::Var(name))
-            }
+            },
+            TokenType::THIS => {
+                self.match_token(current.token_type);
+                Ok(Node::This)
+            },
+            TokenType::SUPER => {
+                self.match_token(current.token_type);
+                Ok(Node::Super)
+            },
             _ => {
                 // FIXME: ?
-                Err(Error {
-                    msg: "Unknown variable".to_string(),
-                    pos: self.resolver.resolve_where(self.get_token(None).pos)
-                })
+                Err(Error::parse(ParseError::UnknownExpression, "Unknown variable".to_string(), self.resolver.resolve_where(self.get_token(None).pos)))
             }
         }
     }
@@ -560,8 +814,19 @@ impl Parser {
             },
             TokenType::NUMBER => {
                 self.match_token(current.token_type);
-                let value = current.text.parse::<f64>().unwrap();
-                let node = Node::Number(value);
+                // literals without a point or exponent that fit an i64 are exact
+                let node = match current.text.parse::<i64>() {
+                    Ok(value) => Node::Integer(value),
+                    Err(_) => Node::Number(current.text.parse::<f64>().unwrap())
+                };
+
+                // 100ms is converted like units.convert(100, 'ms')
+                if self.get_token(None).token_type == TokenType::UNIT {
+                    let unit = self.get_token(None);
+                    self.match_token(TokenType::UNIT);
+
+                    return Ok(Node::Unit(Box::new(node), unit.text, self.resolver.resolve_where(unit.pos)))
+                }
 
                 // FIXME: variables in first place
                 if self.match_token(TokenType::DOTDOT) {
@@ -585,7 +850,7 @@ impl Parser {
             TokenType::LBRACKET => {
                 self.match_token(TokenType::LBRACKET);
                 let mut values = vec![];
-                while !self.match_token(TokenType::RBRACKET) {
+                while self.is_open(TokenType::RBRACKET) {
                     values.push(Box::new(self.expression()?));
                     self.match_token(TokenType::COMMA);   
                 }
@@ -595,8 +860,8 @@ impl Parser {
             TokenType::LBRACE => {
                 self.match_token(TokenType::LBRACE);
                 let mut map = BTreeMap::new();
-                while !self.match_token(TokenType::RBRACE) {
-                    let name = self.consume_token(TokenType::WORD).text;
+                while self.is_open(TokenType::RBRACE) {
+                    let name = self.object_key();
                     self.consume_token(TokenType::COLON);
                     map.insert(name, Box::new(self.expression()?));
                     self.match_token(TokenType::COMMA);   
@@ -604,10 +869,7 @@ impl Parser {
 
                 Ok(Node::Object(map))
             },
-            _ => {
-                // FIXME: ?
-                panic!("Unknown value")
-            }
+            _ => Err(Error::parse(ParseError::UnknownExpression, "Unknown value".to_string(), self.resolver.resolve_where(current.pos)))
         }
     }
 
@@ -759,21 +1021,75 @@ impl Parser {
 
     pub fn unary_expression(&mut self) -> Result<Node, Error> {
         if self.match_token(TokenType::MINUS) {
-            return Ok(Node::Unary(UnaryOp::MINUS, Box::new(self.expression()?)))
+            return Ok(Node::Unary(UnaryOp::MINUS, Box::new(self.unary_expression()?)))
         } else if self.match_token(TokenType::EXCL) {
-            return Ok(Node::Unary(UnaryOp::NOT, Box::new(self.expression()?)));
+            return Ok(Node::Unary(UnaryOp::NOT, Box::new(self.unary_expression()?)));
+        } else if self.match_token(TokenType::TYPEOF) {
+            return Ok(Node::Unary(UnaryOp::TYPEOF, Box::new(self.unary_expression()?)));
+        }
+
+        self.postfix_expression()
+    }
+
+    // a++ and a-- change the variable by one and evaluate to its previous value
+    pub fn postfix_expression(&mut self) -> Result<Node, Error> {
+        let operand = self.primary_expression()?;
+
+        if !matches!(operand, Node::Var(_) | Node::This | Node::FieldAccess(..)) {
+            return Ok(operand)
+        }
+
+        let op = if self.match_token(TokenType::PLUSPLUS) {
+            AssignmentOp::PLUSPLUS
+        } else if self.match_token(TokenType::MINUSMINUS) {
+            AssignmentOp::MINUSMINUS
+        } else {
+            return Ok(operand)
+        };
+
+        Ok(Node::AssignOp(op, Box::new(operand), Box::new(Node::Integer(1))))
+    }
+
+    // keys in object literals: words, strings, numbers and booleans.
+    // numbers and booleans are written the same way `Value::as_key` converts them
+    fn object_key(&mut self) -> String {
+        let current = self.get_token(None);
+
+        match current.token_type {
+            TokenType::STRING | TokenType::BOOLEAN => {
+                self.match_token(current.token_type);
+                current.text
+            },
+            TokenType::NUMBER => {
+                self.match_token(current.token_type);
+                match current.text.parse::<i64>() {
+                    Ok(value) => value.to_string(),
+                    Err(_) => format_number(current.text.parse::<f64>().unwrap())
+                }
+            },
+            _ => self.consume_token(TokenType::WORD).text
+        }
+    }
+
+    // loops reading up to a closing token stop at the end of input,
+    // or when a token didn't match and parsing already failed
+    fn is_open(&mut self, closing: TokenType) -> bool {
+        if self.match_token(closing) || self.error.is_some() {
+            return false
+        }
+
+        if self.get_token(None).token_type == TokenType::EOF {
+            self.error = Some(Error::parse(ParseError::UnexpectedEnd, "Unexpected end of input".to_string(), self.resolver.resolve_where(self.get_token(None).pos)));
+            return false
         }
 
-        self.primary_expression()
+        true
     }
 
     pub fn consume_token(&mut self, token_type: TokenType) -> Token {
         let current = self.get_token(None);
-        if current.token_type != token_type {
-            self.resolver.exit_error(
-                format!("Token {:#?} didnt match {:#?}", token_type, current.token_type),
-                vec![0,0]
-            );
+        if current.token_type != token_type && self.error.is_none() {
+            self.error = Some(Error::parse(ParseError::UnexpectedToken, format!("Token {:#?} didnt match {:#?}", token_type, current.token_type), self.resolver.resolve_where(current.pos)));
         }
 
         self.pos += 1;
@@ -792,14 +1108,148 @@ impl Parser {
 
     pub fn get_token(&self, pos: Option<usize>) -> Token {
         let current = self.pos + pos.unwrap_or(0);
-        if current >= self.tokens.len() {
-            return Token { 
+
+        match self.tokens.get(current) {
+            Some(token) => token.to_owned(),
+            None => Token { 
                 token_type: TokenType::EOF, 
                 text: "\0".to_string(), 
                 pos: self.tokens.len() + 1
             }
         }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::{eval, lexer::Lexer, interpreter::types::Value, ErrorKind, ParseError, Resolver};
+
+    use super::{Node, Parser};
 
-        self.tokens.iter().peekable().nth(current).unwrap().to_owned()
+    fn parse(code: &str) -> Node {
+        let resolver = Resolver::new("<test>".to_string(), code.to_string());
+        let mut lexer = Lexer::new(code, &resolver);
+        lexer.analyse().unwrap();
+
+        Parser::new(lexer.tokens, &resolver).parse().unwrap()
+    }
+
+    fn classify(n: i64) -> Value {
+        eval(&format!("
+            let n = {}
+            let size = null
+            if (n < 0) size = 'negative'
+            else if (n == 0) size = 'zero'
+            else size = 'positive'
+        ", n)).unwrap().get("size".to_string())
+    }
+
+    #[test]
+    fn three_way_chain() {
+        assert_eq!(classify(-4), Value::String("negative".to_string()));
+        assert_eq!(classify(0), Value::String("zero".to_string()));
+        assert_eq!(classify(9), Value::String("positive".to_string()));
+    }
+
+    #[test]
+    fn dangling_else_binds_to_the_nearest_if() {
+        let inner_else = match parse("if (a) if (b) x else y") {
+            Node::BlockStatement(statements) => match *statements[0].to_owned() {
+                Node::IfElseStatement(_, inner, outer_else) => {
+                    assert_eq!(*outer_else, None);
+                    match *inner {
+                        Node::IfElseStatement(_, _, inner_else) => *inner_else,
+                        node => panic!("expected the nested if, got {:?}", node)
+                    }
+                },
+                node => panic!("expected an if, got {:?}", node)
+            },
+            node => panic!("expected a block, got {:?}", node)
+        };
+        assert_eq!(inner_else, Some(Node::Var("y".to_string())));
+
+        let result = |a: bool| eval(&format!("
+            let result = 'untouched'
+            if ({}) if (false) result = 'inner' else result = 'nested else'
+        ", a)).unwrap().get("result".to_string());
+
+        assert_eq!(result(true), Value::String("nested else".to_string()));
+        assert_eq!(result(false), Value::String("untouched".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn loop_else_runs_unless_the_loop_breaks() {
+        let scope = eval("
+            let completed = null
+            for (x in [1, 2]) { } else { completed = 'for' }
+            let i = 0
+            while (i < 2) { i += 1 } else { completed = completed + ' while' }
+            let broken = 'no'
+            for (x in [1, 2]) { break } else { broken = 'yes' }
+        ").unwrap();
+
+        assert_eq!(scope.get("completed".to_string()), Value::String("for while".to_string()));
+        assert_eq!(scope.get("broken".to_string()), Value::String("no".to_string()));
+    }
+
+    #[test]
+    fn a_loop_as_the_body_of_an_if_leaves_the_else_to_the_if() {
+        let result = |c: bool| eval(&format!("
+            let result = null
+            if ({}) while (false) {{ }} else {{ result = 'if else' }}
+        ", c)).unwrap().get("result".to_string());
+
+        assert_eq!(result(true), Value::Null);
+        assert_eq!(result(false), Value::String("if else".to_string()));
+
+        let scope = eval("
+            let result = null
+            if (true) { while (false) { } else { result = 'loop else' } }
+        ").unwrap();
+        assert_eq!(scope.get("result".to_string()), Value::String("loop else".to_string()));
+    }
+
+    #[test]
+    fn break_and_continue_need_a_loop() {
+        assert_eq!(eval("if (true) { continue }").unwrap_err().kind(), ErrorKind::Parse(ParseError::OutsideLoop));
+        assert_eq!(eval("break").unwrap_err().kind(), ErrorKind::Parse(ParseError::OutsideLoop));
+        // a function body is not part of the loop it is defined in
+        assert_eq!(eval("for (x in [1]) { fun f() { break } }").unwrap_err().kind(), ErrorKind::Parse(ParseError::OutsideLoop));
+        assert!(eval("while (true) { if (true) { break } }").is_ok());
+    }
+
+    #[test]
+    fn braced_else_if_chains_select_each_branch() {
+        let grade = |score: i64| eval(&format!("
+            let score = {}
+            let grade = null
+            if (score >= 90) {{
+                grade = 'a'
+            }} else if (score >= 80) {{
+                grade = 'b'
+            }} else if (!(score < 70)) {{
+                grade = 'c'
+            }} else {{
+                grade = 'f'
+            }}
+        ", score)).unwrap().get("grade".to_string());
+
+        assert_eq!(grade(95), Value::String("a".to_string()));
+        assert_eq!(grade(85), Value::String("b".to_string()));
+        assert_eq!(grade(70), Value::String("c".to_string()));
+        assert_eq!(grade(-1), Value::String("f".to_string()));
+    }
+
+    #[test]
+    fn object_keys_can_be_strings() {
+        let scope = eval("
+            let obj = { 'my-key': 1, \"with space\": 2, plain: 3 }
+            let dashed = obj['my-key']
+            let spaced = obj['with space']
+            let plain = obj['plain']
+        ").unwrap();
+
+        assert_eq!(scope.get("dashed".to_string()), Value::Integer(1));
+        assert_eq!(scope.get("spaced".to_string()), Value::Integer(2));
+        assert_eq!(scope.get("plain".to_string()), Value::Integer(3));
+    }
+}